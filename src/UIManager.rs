@@ -2,12 +2,14 @@ use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Gauge, Paragraph, Wrap},
+    widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph, Wrap},
     text::{Line, Span},
 };
 use ratatui::layout::Alignment;
 use ratatui::prelude::Rect;
 use crate::App;
+use crate::playlist::Column;
+use crate::review::ReviewSession;
 
 pub fn ui(f: &mut Frame, app: &App) {
     let size = f.size();
@@ -31,59 +33,11 @@ pub fn ui(f: &mut Frame, app: &App) {
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(title, chunks[0]);
 
-    // Middle area: split horizontally
-    let middle_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage(40),
-            Constraint::Percentage(60),
-        ])
-        .split(chunks[1]);
-
-    // Left: Status indicators
-    let bool_statuses = [
-        ("Drive detected", app.drive_detected),
-        ("Desktop detected", app.desktop_detected),
-        ("Playlist detected", app.playlist_detected),
-        ("Track map created", app.track_map_created),
-    ];
-
-    let bool_lines: Vec<Line> = bool_statuses.iter().map(|(label, state)| {
-        let color = if *state { Color::Green } else { Color::Red };
-        let dot = Span::styled("● ", Style::default().fg(color));
-        Line::from(vec![dot, Span::raw(*label)])
-    }).collect();
-
-    let bool_paragraph = Paragraph::new(bool_lines)
-        .block(Block::default().title("App Statuses").borders(Borders::ALL))
-        .wrap(Wrap { trim: true });
-    f.render_widget(bool_paragraph, middle_chunks[0]);
-
-    // Right: Status + Errors
-    let right_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(40),
-            Constraint::Percentage(60),
-        ])
-        .split(middle_chunks[1]);
-
-    let status_paragraph = Paragraph::new(app.status_message.clone())
-        .block(Block::default().title("Status").borders(Borders::ALL))
-        .wrap(Wrap { trim: true });
-    f.render_widget(status_paragraph, right_chunks[0]);
-
-    let error_style = if app.error_message.is_some() {
-        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
-    } else {
-        Style::default()
-    };
-    let error_text = app.error_message.clone().unwrap_or_else(|| "No errors.".into());
-    let error_paragraph = Paragraph::new(error_text)
-        .style(error_style)
-        .block(Block::default().title("Errors").borders(Borders::ALL))
-        .wrap(Wrap { trim: true });
-    f.render_widget(error_paragraph, right_chunks[1]);
+    // Middle area: the review screen replaces the statuses until it is closed
+    match &app.review {
+        Some(session) => ReviewPanels(f, chunks[1], session),
+        None => MainPanels(f, chunks[1], app),
+    }
 
     // Inside the progress chunk, split vertically into two parts:
     // 1) Gauge (progress bar) top 3 lines
@@ -117,7 +71,7 @@ pub fn ui(f: &mut Frame, app: &App) {
                 .bg(Color::Black)
                 .add_modifier(Modifier::BOLD),
         )
-        .ratio(app.progress as f64);
+        .ratio(app.progress);
     f.render_widget(gauge, gauge_area);
 
     // Render the progress info text in the bottom half of the box
@@ -147,18 +101,236 @@ pub fn ui(f: &mut Frame, app: &App) {
     f.render_widget(current_file_paragraph, chunks[3]);
 
     // Controls hint bar
-    let controls_line = Line::from(vec![
+    let controls_line = match &app.review {
+        Some(session) => ReviewControls(session),
+        None => MainControls(),
+    };
+    let controls_paragraph = Paragraph::new(controls_line)
+        .block(Block::default().borders(Borders::ALL).title("Controls"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(controls_paragraph, chunks[4]);
+}
+
+// Statuses, drives, errors and warnings
+fn MainPanels(f: &mut Frame, area: Rect, app: &App) {
+    // Middle area: split horizontally
+    let middle_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ])
+        .split(area);
+
+    // Left: Status indicators
+    let bool_statuses = [
+        ("Drive detected", app.drive_detected),
+        ("Desktop detected", app.desktop_detected),
+        ("Playlist detected", app.playlist_detected),
+        ("Track map created", app.track_map_created),
+    ];
+
+    let bool_lines: Vec<Line> = bool_statuses.iter().map(|(label, state)| {
+        let color = if *state { Color::Green } else { Color::Red };
+        let dot = Span::styled("● ", Style::default().fg(color));
+        Line::from(vec![dot, Span::raw(*label)])
+    }).collect();
+
+    // Left column holds the statuses above the list of connected rekordbox drives
+    let left_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(6),
+            Constraint::Min(3),
+        ])
+        .split(middle_chunks[0]);
+
+    let bool_paragraph = Paragraph::new(bool_lines)
+        .block(Block::default().title("App Statuses").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(bool_paragraph, left_chunks[0]);
+
+    // Drive list, the highlighted drive is the one [R] will run against
+    let drive_items: Vec<ListItem> = app.drives.iter().map(|drive| {
+        let label = if drive.label.is_empty() { "Unnamed drive".to_string() } else { drive.label.clone() };
+        ListItem::new(vec![
+            Line::from(Span::styled(label, Style::default().add_modifier(Modifier::BOLD))),
+            Line::from(format!("  {}", drive.mount_point.display())),
+            Line::from(format!("  {} free of {} | {}", FormatBytes(drive.available_space), FormatBytes(drive.total_space),
+                drive.track_count.map_or("counting tracks...".to_string(), |count| format!("{} tracks", count)))),
+        ])
+    }).collect();
+
+    let drive_title = if app.drives.is_empty() { "Drives (none found)".to_string() } else { format!("Drives ({})", app.drives.len()) };
+    let drive_list = List::new(drive_items)
+        .block(Block::default().title(drive_title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    let mut drive_state = ListState::default();
+    if !app.drives.is_empty() {
+        drive_state.select(Some(app.selected_drive));
+    }
+    f.render_stateful_widget(drive_list, left_chunks[1], &mut drive_state);
+
+    // Right: Status + Errors + Warnings
+    let right_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(30),
+            Constraint::Percentage(35),
+            Constraint::Percentage(35),
+        ])
+        .split(middle_chunks[1]);
+
+    let status_paragraph = Paragraph::new(app.status_message.clone())
+        .block(Block::default().title("Status").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(status_paragraph, right_chunks[0]);
+
+    let error_style = if app.error_message.is_some() {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    let error_text = app.error_message.clone().unwrap_or_else(|| "No errors.".into());
+    let error_paragraph = Paragraph::new(error_text)
+        .style(error_style)
+        .block(Block::default().title("Errors").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(error_paragraph, right_chunks[1]);
+
+    // Warnings stay up for the whole session, e.g. playlist files that could not be decoded
+    let warning_lines: Vec<Line> = if app.warnings.is_empty() {
+        vec![Line::from("No warnings.")]
+    } else {
+        app.warnings.iter().map(|w| Line::from(Span::styled(w.clone(), Style::default().fg(Color::Yellow)))).collect()
+    };
+    let warning_paragraph = Paragraph::new(warning_lines)
+        .block(Block::default().title(format!("Warnings ({})", app.warnings.len())).borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(warning_paragraph, right_chunks[2]);
+}
+
+fn MainControls() -> Line<'static> {
+    Line::from(vec![
         Span::styled("[Q]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
         Span::raw(" Exit   "),
         Span::styled("[S]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
         Span::raw(" Scan Drives   "),
+        Span::styled("[↑/↓]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(" Select Drive   "),
         Span::styled("[R]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
         Span::raw(" Run RekordScratch   "),
+        Span::styled("[D]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(" Dry Run   "),
         Span::styled("[P]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
         Span::raw(" Set Playlists Path"),
-    ]);
-    let controls_paragraph = Paragraph::new(controls_line)
-        .block(Block::default().borders(Borders::ALL).title("Controls"))
+    ])
+}
+
+// The after-run review: the track in question on the left, the playlist rows it could belong to on the right
+fn ReviewPanels(f: &mut Frame, area: Rect, session: &ReviewSession) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ])
+        .split(area);
+
+    let Some(item) = session.Current() else { return; };
+
+    let mut details = vec![
+        Line::from(Span::styled(item.reason.Describe(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(format!("File: {}", item.track.path.display())),
+        Line::from(format!("Searched for: {}", item.query)),
+        Line::from(""),
+    ];
+    if item.copies.is_empty() {
+        details.push(Line::from("Not copied anywhere yet."));
+    } else {
+        details.push(Line::from("Currently copied to:"));
+        details.extend(item.copies.iter().map(|copy| Line::from(format!("  {}", copy.display()))));
+    }
+
+    let detail_paragraph = Paragraph::new(details)
+        .block(Block::default().title(format!("Review {}/{}", session.current + 1, session.items.len())).borders(Borders::ALL))
         .wrap(Wrap { trim: true });
-    f.render_widget(controls_paragraph, chunks[4]);
+    f.render_widget(detail_paragraph, columns[0]);
+
+    // Search box sits above the candidates while typing
+    let candidate_area = match &session.search {
+        Some(search) => {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Min(3)])
+                .split(columns[1]);
+            let search_paragraph = Paragraph::new(format!("{}_", search))
+                .block(Block::default().title("Search").borders(Borders::ALL));
+            f.render_widget(search_paragraph, split[0]);
+            split[1]
+        }
+        None => columns[1],
+    };
+
+    let candidate_items: Vec<ListItem> = item.candidates.iter().map(|(entry, score)| {
+        let artist = entry.Get(Column::Artist).unwrap_or("?");
+        ListItem::new(vec![
+            Line::from(Span::styled(format!("{} - {}", artist, entry.Title()), Style::default().add_modifier(Modifier::BOLD))),
+            Line::from(format!("  {} #{} | {:.0}% similar", entry.PlaylistName(), entry.position, score * 100.0)),
+        ])
+    }).collect();
+
+    let candidate_title = if item.candidates.is_empty() { "Candidates (none, try [/] Search)".to_string() }
+        else { format!("Candidates ({})", item.candidates.len()) };
+    let candidate_list = List::new(candidate_items)
+        .block(Block::default().title(candidate_title).borders(Borders::ALL))
+        .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+
+    let mut candidate_state = ListState::default();
+    if !item.candidates.is_empty() {
+        candidate_state.select(Some(session.selected));
+    }
+    f.render_stateful_widget(candidate_list, candidate_area, &mut candidate_state);
+}
+
+fn ReviewControls(session: &ReviewSession) -> Line<'static> {
+    if session.search.is_some() {
+        return Line::from(vec![
+            Span::styled("[Enter]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Search   "),
+            Span::styled("[Esc]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Cancel"),
+        ]);
+    }
+
+    Line::from(vec![
+        Span::styled("[↑/↓]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(" Select Playlist Row   "),
+        Span::styled("[Enter]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(" Assign   "),
+        Span::styled("[N]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(" Skip   "),
+        Span::styled("[/]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(" Search   "),
+        Span::styled("[Esc]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(" Finish Review   "),
+        Span::styled("[Q]", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(" Exit"),
+    ])
+}
+
+// Formats a byte count for the drive list
+fn FormatBytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
 }
@@ -1,20 +1,38 @@
+use std::path::PathBuf;
+use crate::review::ReviewSession;
+
+pub struct DriveCandidate {
+    pub label: String,
+    pub mount_point: PathBuf,
+    pub total_space: u64,
+    pub available_space: u64,
+    // None until the background count has walked the drive
+    pub track_count: Option<usize>,
+}
+
 pub struct App {
     pub track_map_created: bool,
     pub desktop_detected: bool,
     pub playlist_detected: bool,
     pub drive_detected: bool,
     
-    pub drive_letter: Option<String>,
+    pub drive_path: Option<PathBuf>,
+    pub drives: Vec<DriveCandidate>,
+    pub selected_drive: usize,
     pub current_file: Option<String>,
     
     pub status_message: String,
     pub error_message: Option<String>,
+    pub warnings: Vec<String>,
     
     pub progress: f64, // 0.0 -> 1.0
     pub is_mp3_copying: bool,
 
     pub files_cleared: usize,
-    pub files_total: usize
+    pub files_total: usize,
+
+    // Open after a run that left tracks to check by hand
+    pub review: Option<ReviewSession>,
 }
 
 impl App {
@@ -27,14 +45,19 @@ impl App {
             playlist_detected: false,
 
             drive_detected: false,
-            drive_letter: None,
+            drive_path: None,
+            drives: Vec::new(),
+            selected_drive: 0,
             status_message: "Starting...".into(),
             error_message: None,
+            warnings: Vec::new(),
             progress: 0.0,
             current_file: None,
             
             files_cleared: 0,
             files_total: 0,
+
+            review: None,
         }
     }
 
@@ -43,8 +66,12 @@ impl App {
         self.error_message = Some(msg.into());
     }
 
-    pub fn SetDriveLetter(&mut self, msg: impl Into<String>) {
-        self.drive_letter = Some(msg.into());
+    pub fn AddWarning(&mut self, msg: impl Into<String>) {
+        self.warnings.push(msg.into());
+    }
+
+    pub fn SetDrivePath(&mut self, path: impl Into<PathBuf>) {
+        self.drive_path = Some(path.into());
     }
 
     pub fn SetStatusMessage(&mut self, msg: impl Into<String>) {
@@ -74,4 +101,42 @@ impl App {
     pub fn SetPlaylistStatus(&mut self, status: impl Into<bool>) {
         self.playlist_detected = status.into();
     }
+
+    // Drive selection
+    // Keeps the same stick selected across rescans where possible
+    pub fn SetDrives(&mut self, drives: Vec<DriveCandidate>) {
+        let previous = self.drive_path.clone();
+        self.drives = drives;
+        self.selected_drive = previous
+            .and_then(|path| self.drives.iter().position(|d| d.mount_point == path))
+            .unwrap_or(0);
+        self.SyncSelectedDrive();
+    }
+
+    pub fn SelectNextDrive(&mut self) {
+        if !self.drives.is_empty() {
+            self.selected_drive = (self.selected_drive + 1) % self.drives.len();
+            self.SyncSelectedDrive();
+        }
+    }
+
+    pub fn SelectPreviousDrive(&mut self) {
+        if !self.drives.is_empty() {
+            self.selected_drive = (self.selected_drive + self.drives.len() - 1) % self.drives.len();
+            self.SyncSelectedDrive();
+        }
+    }
+
+    fn SyncSelectedDrive(&mut self) {
+        match self.drives.get(self.selected_drive).map(|d| d.mount_point.clone()) {
+            Some(path) => {
+                self.SetDrivePath(path);
+                self.SetDriveStatus(true);
+            }
+            None => {
+                self.drive_path = None;
+                self.SetDriveStatus(false);
+            }
+        }
+    }
 }
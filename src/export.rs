@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::io::Write;
+use std::fs::File;
+use std::io;
+use crate::playlist::{Column, PlaylistTrack};
+use crate::track::Track;
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Placed tracks
+
+// A recovered file and the playlist row it was placed for
+// One source file in three playlists gives three of these
+#[derive(Clone, Debug)]
+pub struct PlacedTrack {
+    pub entry: PlaylistTrack,
+    pub destination: PathBuf,
+    // The source file's metadata, read once during the run
+    pub track: Track,
+}
+
+// What playlist files need to know about a copied track
+struct TrackInfo {
+    seconds: i64,
+    artist: String,
+    title: String,
+    album: String,
+    genre: String,
+    bitrate: u32,
+    sample_rate: u32,
+}
+
+// This takes duration and tags from the source file's Track, falling back to the playlist row
+// RETURNS: Track info, duration is -1 when the file could not be read (M3U's "unknown")
+fn ReadTrackInfo(placed: &PlacedTrack) -> TrackInfo {
+    let row = |column| placed.entry.Get(column).unwrap_or_default().to_string();
+    let track = &placed.track;
+
+    TrackInfo {
+        seconds: track.seconds.map_or(-1, |s| s as i64),
+        artist: track.artist.clone().unwrap_or_else(|| row(Column::Artist)),
+        title: track.title.clone().unwrap_or_else(|| placed.entry.Title().to_string()),
+        album: track.album.clone().unwrap_or_else(|| row(Column::Album)),
+        genre: track.genre.clone().unwrap_or_else(|| row(Column::Genre)),
+        bitrate: track.bitrate.unwrap_or(0),
+        sample_rate: track.sample_rate.unwrap_or(0),
+    }
+}
+
+// This groups placements by playlist, each playlist in running order
+// RETURNS: Playlist name -> placements
+fn GroupByPlaylist(placed: &[PlacedTrack]) -> BTreeMap<String, Vec<&PlacedTrack>> {
+    let mut playlists: BTreeMap<String, Vec<&PlacedTrack>> = BTreeMap::new();
+    for track in placed {
+        playlists.entry(track.entry.PlaylistName()).or_default().push(track);
+    }
+    for tracks in playlists.values_mut() {
+        tracks.sort_by_key(|t| t.entry.position);
+    }
+    playlists
+}
+
+// This expresses a path relative to a directory, so playlist files survive moving RekordCrates
+// RETURNS: Relative path using forward slashes, or the absolute path if there is no common root
+fn RelativePath(fromDir: &Path, to: &Path) -> String {
+    let from: Vec<Component> = fromDir.components().collect();
+    let target: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    if common == 0 {
+        return to.to_string_lossy().to_string();
+    }
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(target[common..].iter().map(|c| c.as_os_str().to_string_lossy().to_string()));
+    parts.join("/")
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: M3U8 playlists
+
+// This writes one .m3u8 per playlist into the output root, ordered as in the source playlist
+// RETURNS: Number of playlist files written
+pub fn WriteM3uPlaylists(outputRoot: &Path, placed: &[PlacedTrack]) -> io::Result<usize> {
+    let playlists = GroupByPlaylist(placed);
+
+    for (name, tracks) in &playlists {
+        let m3uPath = outputRoot.join(format!("{}.m3u8", name));
+        let m3uDir = m3uPath.parent().unwrap_or(outputRoot).to_path_buf();
+        std::fs::create_dir_all(&m3uDir)?;
+
+        let mut file = File::create(&m3uPath)?;
+        writeln!(file, "#EXTM3U")?;
+        writeln!(file, "#PLAYLIST:{}", name)?;
+
+        for track in tracks {
+            let info = ReadTrackInfo(track);
+            let label = if info.artist.is_empty() { info.title } else { format!("{} - {}", info.artist, info.title) };
+            writeln!(file, "#EXTINF:{},{}", info.seconds, label)?;
+            writeln!(file, "{}", RelativePath(&m3uDir, &track.destination))?;
+        }
+    }
+
+    Ok(playlists.len())
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: rekordbox.xml
+// Rekordbox imports this documented format via Preferences > Advanced > rekordbox xml
+
+// A folder in the PLAYLISTS tree, playlist names containing "/" become nested folders
+#[derive(Default)]
+struct XmlFolder<'a> {
+    folders: BTreeMap<String, XmlFolder<'a>>,
+    playlists: BTreeMap<String, Vec<&'a PlacedTrack>>,
+}
+
+// This writes a rekordbox.xml describing every recovered file and the playlists it belongs to
+// RETURNS: Number of tracks in the collection
+pub fn WriteRekordboxXml(xmlPath: &Path, placed: &[PlacedTrack]) -> io::Result<usize> {
+    // Each source file is one collection entry, however many playlists (and playlist folder copies) it is in
+    // Its Location is the first copy, so re-importing does not give Rekordbox one duplicate per playlist
+    let mut trackIds: BTreeMap<&Path, usize> = BTreeMap::new();
+    let mut collection: Vec<&PlacedTrack> = Vec::new();
+    for track in placed {
+        if !trackIds.contains_key(track.track.path.as_path()) {
+            trackIds.insert(&track.track.path, collection.len() + 1);
+            collection.push(track);
+        }
+    }
+
+    let mut root = XmlFolder::default();
+    for (name, tracks) in GroupByPlaylist(placed) {
+        let mut parts: Vec<&str> = name.split('/').collect();
+        let playlistName = parts.pop().unwrap_or_default().to_string();
+
+        let mut folder = &mut root;
+        for part in parts {
+            folder = folder.folders.entry(part.to_string()).or_default();
+        }
+        folder.playlists.insert(playlistName, tracks);
+    }
+
+    let mut file = io::BufWriter::new(File::create(xmlPath)?);
+    writeln!(file, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(file, "<DJ_PLAYLISTS Version=\"1.0.0\">")?;
+    writeln!(file, "  <PRODUCT Name=\"RekordScratch\" Version=\"{}\" Company=\"\"/>", env!("CARGO_PKG_VERSION"))?;
+
+    writeln!(file, "  <COLLECTION Entries=\"{}\">", collection.len())?;
+    for (i, track) in collection.iter().enumerate() {
+        WriteXmlTrack(&mut file, i + 1, track)?;
+    }
+    writeln!(file, "  </COLLECTION>")?;
+
+    writeln!(file, "  <PLAYLISTS>")?;
+    WriteXmlFolder(&mut file, "ROOT", &root, &trackIds, 2)?;
+    writeln!(file, "  </PLAYLISTS>")?;
+    writeln!(file, "</DJ_PLAYLISTS>")?;
+    file.flush()?;
+
+    Ok(collection.len())
+}
+
+// This writes one COLLECTION entry, tags win over the playlist row where both exist
+// RETURNS: Nothing, writes to the xml
+fn WriteXmlTrack(file: &mut impl Write, trackId: usize, track: &PlacedTrack) -> io::Result<()> {
+    let info = ReadTrackInfo(track);
+    let row = |column| track.entry.Get(column).unwrap_or_default();
+    let size = std::fs::metadata(&track.destination).map(|m| m.len()).unwrap_or(0);
+
+    let attributes = [
+        ("TrackID", trackId.to_string()),
+        ("Name", info.title),
+        ("Artist", info.artist),
+        ("Composer", row(Column::Composer).to_string()),
+        ("Album", info.album),
+        ("Genre", info.genre),
+        ("Kind", FileKind(&track.destination)),
+        ("Size", size.to_string()),
+        ("TotalTime", info.seconds.max(0).to_string()),
+        ("Year", row(Column::Year).to_string()),
+        ("AverageBpm", row(Column::Bpm).to_string()),
+        ("BitRate", info.bitrate.to_string()),
+        ("SampleRate", info.sample_rate.to_string()),
+        ("Comments", row(Column::Comments).to_string()),
+        ("Remixer", row(Column::Remixer).to_string()),
+        ("Tonality", row(Column::Key).to_string()),
+        ("Label", row(Column::Label).to_string()),
+        ("Mix", row(Column::MixName).to_string()),
+        ("Location", FileUrl(&track.destination)),
+    ];
+
+    write!(file, "    <TRACK")?;
+    for (name, value) in attributes {
+        write!(file, " {}=\"{}\"", name, XmlEscape(&value))?;
+    }
+    writeln!(file, "/>")
+}
+
+// This writes a folder node (Type 0) and everything beneath it, playlists are Type 1 nodes
+// RETURNS: Nothing, writes to the xml
+fn WriteXmlFolder(file: &mut impl Write, name: &str, folder: &XmlFolder, trackIds: &BTreeMap<&Path, usize>, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    writeln!(file, "{}<NODE Type=\"0\" Name=\"{}\" Count=\"{}\">", indent, XmlEscape(name), folder.folders.len() + folder.playlists.len())?;
+
+    for (childName, child) in &folder.folders {
+        WriteXmlFolder(file, childName, child, trackIds, depth + 1)?;
+    }
+
+    for (playlistName, tracks) in &folder.playlists {
+        writeln!(file, "{}  <NODE Name=\"{}\" Type=\"1\" KeyType=\"0\" Entries=\"{}\">", indent, XmlEscape(playlistName), tracks.len())?;
+        for track in tracks {
+            writeln!(file, "{}    <TRACK Key=\"{}\"/>", indent, trackIds[track.track.path.as_path()])?;
+        }
+        writeln!(file, "{}  </NODE>", indent)?;
+    }
+
+    writeln!(file, "{}</NODE>", indent)
+}
+
+// This names the file type the way Rekordbox's own exports do
+// RETURNS: Kind attribute value
+fn FileKind(path: &Path) -> String {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_uppercase()).unwrap_or_default();
+    match extension.as_str() {
+        "AIF" => "AIFF File".to_string(),
+        "" => "File".to_string(),
+        other => format!("{} File", other),
+    }
+}
+
+// This builds the file://localhost/ URL Rekordbox expects in Location attributes
+// RETURNS: Percent encoded URL
+fn FileUrl(path: &Path) -> String {
+    let absolute = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut text = absolute.to_string_lossy().replace('\\', "/");
+
+    // Windows verbatim prefix from canonicalize
+    if let Some(stripped) = text.strip_prefix("//?/") {
+        text = stripped.to_string();
+    }
+
+    let mut url = String::from("file://localhost");
+    if !text.starts_with('/') {
+        url.push('/');
+    }
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' | b':' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+fn XmlEscape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::ScratchDir;
+
+    fn Placed(source: &str, playlist: &str, destination: &str) -> PlacedTrack {
+        let headers = vec!["Track Title".to_string(), "Artist".to_string()];
+        let columns = vec![Some(Column::Title), Some(Column::Artist)];
+        let entry = PlaylistTrack::FromCells(&["Song".to_string(), "DJ X".to_string()], 1, &headers, &columns, playlist);
+        PlacedTrack { entry, destination: PathBuf::from(destination), track: Track::Untagged(Path::new(source)) }
+    }
+
+    #[test]
+    fn RekordboxXmlListsEachSourceOnce() {
+        let placed = vec![
+            Placed("/usb/song.mp3", "A.txt", "/out/A/song.mp3"),
+            Placed("/usb/song.mp3", "B.txt", "/out/B/song.mp3"),
+            Placed("/usb/song.mp3", "Folder/C.txt", "/out/Folder/C/song.mp3"),
+            Placed("/usb/other.mp3", "A.txt", "/out/A/other.mp3"),
+        ];
+        let out = ScratchDir::New("export");
+        let xmlPath = out.Path().join("rekordbox.xml");
+
+        assert_eq!(WriteRekordboxXml(&xmlPath, &placed).unwrap(), 2);
+        let xml = std::fs::read_to_string(&xmlPath).unwrap();
+        assert_eq!(xml.matches("<TRACK TrackID=").count(), 2);
+        assert!(xml.contains("/out/A/song.mp3"));
+        assert!(!xml.contains("/out/B/song.mp3"));
+        assert_eq!(xml.matches("<TRACK Key=\"1\"/>").count(), 3);
+    }
+
+    #[test]
+    fn RelativePathWalksUpToTheCommonFolder() {
+        assert_eq!(RelativePath(Path::new("/out/RekordCrates"), Path::new("/out/RekordCrates/Set/song.mp3")), "Set/song.mp3");
+        assert_eq!(RelativePath(Path::new("/out/RekordCrates/Techno"), Path::new("/out/RekordCrates/Tracks/song.mp3")), "../Tracks/song.mp3");
+    }
+}
@@ -1,5 +1,17 @@
 #![allow(non_snake_case)]
+#![allow(clippy::needless_return, clippy::collapsible_if)]
 mod app;
+mod matching;
+mod overrides;
+mod export;
+mod plan;
+mod playlist;
+mod pool;
+mod readers;
+mod review;
+#[cfg(test)]
+mod scratch;
+mod track;
 mod UIManager;
 
 use crossterm::{
@@ -7,23 +19,31 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}
 };
-use lofty::{read_from_path, ItemKey, TaggedFileExt};
+use lofty::{read_from_path, Accessor, Tag, TagExt, TaggedFileExt};
 use sysinfo::{System, SystemExt, DiskExt};
-use std::io::{BufRead, BufReader, Write};
+use std::io::Write;
 use ratatui::backend::CrosstermBackend;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
-use std::path::{Path};
+use std::path::{Path, PathBuf};
 use ratatui::Terminal;
 use walkdir::WalkDir;
 use std::fs::File;
 use UIManager::ui;
-use clap::Parser;
-use app::App;
+use clap::{Parser, Subcommand};
+use app::{App, DriveCandidate};
+use playlist::{BuildMapFromPlaylists, BuildMapFromXml, IsRekordboxXml, Column, PlaylistTrack, SanitiseFileName, TrackMap};
+use export::{PlacedTrack, WriteM3uPlaylists, WriteRekordboxXml};
+use matching::{FileNamePattern, FindByFileName, FindPlaylistEntries, MatchReport, MatchResult, MatchRule, MatchSettings, ParseFileName, RankCandidates, DefaultFileNamePatterns, TrackIndex, TrackQuery};
+use overrides::{DefaultOverridesPath, MatchOverride, Overrides, ResolveOverride, ResolveRow};
+use track::Track;
+use pool::RunPool;
+use plan::{Collision, CollisionPolicy, CopyJob, PlanFile, PlanPreview, Resolution, SameContents, SuffixedDestination, PLAN_FILE_NAME};
+use review::{ReviewItem, ReviewReason, ReviewSession, CANDIDATE_LIMIT};
 use std::fs;
 use std::io;
-use dirs;
 
 // -------------------------------------------------------------------------------------------------------------------------------------
 // TASKLIST
@@ -37,36 +57,104 @@ use dirs;
 // REGION: Path detection
 
 // This function detects external drives and checks if they are rekordboxed
-// RETURNS: Drive letter
-fn DetectRemovableDrives() -> String {
+// RETURNS: Every rekordboxed drive currently mounted
+fn DetectRemovableDrives() -> Vec<DriveCandidate> {
     let mut sys = System::new_all();
     sys.refresh_disks_list();
+    let mut candidates = Vec::new();
 
     // Iterate through all disks
     for disk in sys.disks() {
         if disk.is_removable() {
-            // Check for rekordboxing 
-            let mountPoint = disk.mount_point().to_string_lossy();
-            if let Some(driveLetter) = mountPoint.chars().next() {
-                let driveLetterString = driveLetter.to_string();
-                if DetectRekordboxMarkers(&driveLetterString) {
-                    return driveLetterString;
-                }
+            // Check for rekordboxing under the full mount point (E:\, /media/me/REKORDBOX, /Volumes/REKORDBOX)
+            let mountPoint = disk.mount_point();
+            if DetectRekordboxMarkers(mountPoint) {
+                candidates.push(DriveCandidate {
+                    label: disk.name().to_string_lossy().to_string(),
+                    mount_point: mountPoint.to_path_buf(),
+                    total_space: disk.total_space(),
+                    available_space: disk.available_space(),
+                    track_count: None,
+                });
             }
         }
     }
-    String::new()
+    candidates
+}
+
+// This builds a drive entry for a user supplied --source directory instead of a detected USB
+// RETURNS: Drive candidate, or an error explaining why the path is unusable
+fn DetectSourceDrive(source: &Path, skipMarkerCheck: bool) -> anyhow::Result<DriveCandidate> {
+    if !source.is_dir() {
+        anyhow::bail!("Source {} is not a directory.", source.display());
+    }
+    if !skipMarkerCheck && !DetectRekordboxMarkers(source) {
+        anyhow::bail!("Source {} has no Contents/PIONEER folders (use --skip-marker-check to override).", source.display());
+    }
+
+    // Space figures come from whichever disk the source lives on (longest matching mount point)
+    let fullPath = source.canonicalize()?;
+    let mut sys = System::new_all();
+    sys.refresh_disks_list();
+    let disk = sys.disks().iter()
+        .filter(|d| fullPath.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len());
+
+    Ok(DriveCandidate {
+        label: fullPath.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        mount_point: source.to_path_buf(),
+        total_space: disk.map_or(0, |d| d.total_space()),
+        available_space: disk.map_or(0, |d| d.available_space()),
+        track_count: None,
+    })
+}
+
+// This counts the tracks a rekordbox stick holds, so sticks can be told apart in the drive list
+// RETURNS: Number of audio files the run will pick up
+fn CountTracksOnDrive(mountPoint: &Path) -> usize {
+    WalkDir::new(mountPoint).into_iter().filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && IsAudioFile(e.path()))
+        .count()
+}
+
+// Every format a stick can hold tracks in, by the name used in the summary, with its extensions
+const AUDIO_FORMATS: &[(&str, &[&str])] = &[
+    ("MP3", &["mp3"]),
+    ("WAV", &["wav"]),
+    ("AIFF", &["aif", "aiff"]),
+    ("FLAC", &["flac"]),
+    ("M4A", &["m4a", "mp4"]),
+    ("OGG", &["ogg"]),
+];
+
+// This works out which audio format a file is from its extension
+// RETURNS: Format name, None if it is not a track we can recover
+fn AudioFormat(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    AUDIO_FORMATS.iter().find(|(_, extensions)| extensions.contains(&extension.as_str())).map(|(name, _)| *name)
+}
+
+// This checks whether a file is a track we can recover
+// RETURNS: Boolean corresponding to if it is a supported audio file
+fn IsAudioFile(path: &Path) -> bool {
+    AudioFormat(path).is_some()
+}
+
+// This lists found and matched tracks per format, in AUDIO_FORMATS order
+// RETURNS: e.g. "120 MP3 (110 matched), 12 FLAC (12 matched)"
+fn FormatSummary(formats: &HashMap<&'static str, (usize, usize)>) -> String {
+    AUDIO_FORMATS.iter()
+        .filter_map(|(name, _)| formats.get(name).map(|(found, matched)| format!("{} {} ({} matched)", found, name, matched)))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 // This function checks if a USB has the standard rekordbox stuff
 // RETURNS: Boolean corresponding to if it is a rekordbox USB
-fn DetectRekordboxMarkers(driveLetter: &str) -> bool {
-    // Assemble path
-    let path = format!("{}:\\", driveLetter); 
-
+fn DetectRekordboxMarkers(mountPoint: &Path) -> bool {
     // Rekordbox sticks have a "Contents" and a "PIONEER" folder
-    let isContents = Path::new(&path).join("Contents").is_dir();
-    let isPioneer = Path::new(&path).join("PIONEER").is_dir();
+    let isContents = mountPoint.join("Contents").is_dir();
+    let isPioneer = mountPoint.join("PIONEER").is_dir();
 
     isContents && isPioneer
 }
@@ -91,64 +179,6 @@ fn GetDesktopPath() -> String {
 // -------------------------------------------------------------------------------------------------------------------------------------
 
 
-// -------------------------------------------------------------------------------------------------------------------------------------
-// REGION: TrackMap construction
-
-// This parses all playlist.txt files in a directory and adds titles to trackMap
-// RETURNS: Nothing, but modifies the trackMap
-fn BuildMapFromTxt(trackMap: &mut HashMap<String, String>, txtPath: &str) -> std::io::Result<()> {
-    // Iterate through all txt files in the directory
-    for entryResult in fs::read_dir(txtPath)? {
-        let entry = entryResult?;
-        let path = entry.path();
-
-        // Check if it is a file and ends in .txt
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                // run parser function
-                if ext == "txt" {
-                    ExtractTitlesFromFile(&path, trackMap)?;
-                }
-            }
-        }
-    }
-    
-    Ok(())
-}
-
-// This populates the hashmap with the titles from the provided txt
-// RETURNS: Error, but main contribution is changing the trackMap
-fn ExtractTitlesFromFile(filepath: &Path, map: &mut HashMap<String, String>) -> std::io::Result<()> {
-    let file = File::open(filepath)?;
-    let reader = BufReader::new(file);
-    let filename = filepath.file_name()
-        .and_then(|f| f.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-
-    // Iterate throigh each entry in file
-    for (i, line_res) in reader.lines().enumerate() {
-        let line = line_res?;
-
-        // Skip header
-        if i == 0 {continue;} 
-   
-        // Third column is track title
-        // Track title ALWAYS exists
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() > 1 {
-            let title = parts[2].trim().to_string();
-
-               map.insert(title, filename.clone());
-        }
-    }
-
-    Ok(())
-}
-
-// ENDREGION
-// -------------------------------------------------------------------------------------------------------------------------------------
-
 // -------------------------------------------------------------------------------------------------------------------------------------
 // REGION: Copy files
 
@@ -168,191 +198,585 @@ fn AppStatus(app: &Arc<Mutex<App>>, msg: String) {
     }
 }
 
-// This gets the title from track metadata
-// RETURNS: String or error
-fn ExtractTitleFromPath(path: &Path) -> anyhow::Result<Option<String>> {
-    let taggedFile = read_from_path(path)?;
+// This function creates the RekordCrates folder on the desktop, reducing user input
+// RETURNS: Nothing, it modifies OS state
+fn CreatePlaylistsFolder(deskPath: &str) {
+    let folder = Path::new(deskPath).join("RekordCrates");
+    fs::create_dir_all(folder).expect("Unable to create playlists folder.");
+}
+
+// Everything a run will write, worked out before any file is copied
+#[derive(Default)]
+struct CopyPlan {
+    // In discovery order, the copies of one source file are next to each other
+    jobs: Vec<CopyJob>,
+    placed: Vec<PlacedTrack>,
+    collisions: Vec<Collision>,
+    policy: CollisionPolicy,
+    // Destination -> source of its copy, keyed case-insensitively since NTFS, APFS and exFAT treat "Track.mp3" and "track.mp3" as one file
+    claimed: HashMap<String, PathBuf>,
+}
+
+impl CopyPlan {
+    fn New(policy: CollisionPolicy) -> Self {
+        Self { policy, ..Self::default() }
+    }
 
-    if let Some(tag) = taggedFile.primary_tag() {
-        if let Some(title) = tag.get_string(&ItemKey::TrackTitle) {
-            return Ok(Some(title.to_string()));
+    // This adds a copy, settling it with the collision policy if another file already claimed the destination
+    // The first file always keeps the name, so parallel writers never write the same path
+    // RETURNS: Where the source ends up, None if it is not copied
+    fn AddJob(&mut self, mut job: CopyJob, artist: Option<&str>) -> Option<PathBuf> {
+        let Some(existing) = self.ClaimedBy(&job.destination) else {
+            let destination = job.destination.clone();
+            self.Claim(&destination, &job.source);
+            self.jobs.push(job);
+            return Some(destination);
+        };
+        if existing == job.source {
+            return Some(job.destination);
+        }
+
+        let (resolution, destination) = match self.policy {
+            CollisionPolicy::Counter => {
+                let renamed = self.FreeDestination(&job.destination, None);
+                (Resolution::Renamed(renamed.clone()), Some(renamed))
+            }
+            CollisionPolicy::Artist => {
+                let renamed = self.FreeDestination(&job.destination, artist.filter(|a| !a.trim().is_empty()).map(SanitiseFileName));
+                (Resolution::Renamed(renamed.clone()), Some(renamed))
+            }
+            CollisionPolicy::SkipIdentical if SameContents(&existing, &job.source) => (Resolution::SharedIdentical, Some(job.destination.clone())),
+            CollisionPolicy::SkipIdentical => {
+                let renamed = self.FreeDestination(&job.destination, None);
+                (Resolution::Renamed(renamed.clone()), Some(renamed))
+            }
+            CollisionPolicy::Fail => (Resolution::Failed, None),
+        };
+        self.collisions.push(Collision { source: job.source.clone(), destination: job.destination.clone(), claimed_by: existing, resolution });
+
+        // A shared identical copy is already planned, only renamed copies are new jobs
+        if let Some(renamed) = destination.as_ref().filter(|d| **d != job.destination) {
+            self.Claim(renamed, &job.source);
+            job.destination = renamed.clone();
+            self.jobs.push(job);
         }
+        destination
     }
-    Ok(None)
-}
 
-// This gets the genre from track metadata
-// RETURNS: String or error
-fn ExtractGenreFromPath(path: &Path) -> anyhow::Result<Option<String>> {
-    let taggedFile = read_from_path(path)?;
-    
-    if let Some(tag) = taggedFile.primary_tag() {
-        if let Some(genre) = tag.get_string(&ItemKey::Genre) {
-            return Ok(Some(genre.to_string()));
+    // This marks a destination as taken by a source, also for files written before the plan was made
+    // RETURNS: Nothing, modifies the plan
+    fn Claim(&mut self, destination: &Path, source: &Path) {
+        self.claimed.insert(DestinationKey(destination), source.to_path_buf());
+    }
+
+    // RETURNS: Source of the copy at a destination, None if it is free
+    fn ClaimedBy(&self, destination: &Path) -> Option<PathBuf> {
+        self.claimed.get(&DestinationKey(destination)).cloned()
+    }
+
+    // This finds the first free name for a colliding copy, "track (Artist).mp3" then "track (2).mp3", "track (3).mp3", ...
+    // RETURNS: Destination nothing in the plan claims yet
+    fn FreeDestination(&self, destination: &Path, suffix: Option<String>) -> PathBuf {
+        if let Some(candidate) = suffix.map(|s| SuffixedDestination(destination, &s)).filter(|c| self.ClaimedBy(c).is_none()) {
+            return candidate;
         }
+        (2..).map(|n| SuffixedDestination(destination, &n.to_string()))
+            .find(|candidate| self.ClaimedBy(candidate).is_none())
+            .unwrap()
     }
+}
 
-    // Default to unknown genre
-    // This is to ensure ALL files get moved, organised or not
-    Ok(Some("Unknown Genre".to_string()))
+// Destinations that differ only in case are the same file on the usual desktop filesystems
+fn DestinationKey(destination: &Path) -> String {
+    destination.to_string_lossy().to_lowercase()
 }
 
-// This function creates the RekordCrates folder on the desktop, reducing user input
+// This works out where a track goes inside its playlist folder
+// RETURNS: Destination path
+fn PlaylistDestination(outputRoot: &Path, entry: &PlaylistTrack, srcPath: &Path, options: &RunOptions) -> PathBuf {
+    // Clean folder name by removing .txt
+    let folder = entry.PlaylistName();
+    outputRoot.join(folder).join(PlaylistFileName(entry, srcPath, options))
+}
+
+// The shared Tracks folder, for runs that only write playlist files
+// RETURNS: Destination path
+fn SharedDestination(outputRoot: &Path, srcPath: &Path) -> PathBuf {
+    outputRoot.join("Tracks").join(srcPath.file_name().unwrap())
+}
+
+// This carries out one planned copy, creating the folders it needs
 // RETURNS: Nothing, it modifies OS state
-fn CreatePlaylistsFolder(deskPath: &str) {
-    let folder = Path::new(deskPath).join("RekordCrates");
-    fs::create_dir_all(folder).expect("Unable to create playlists folder.");
+fn CopyTrack(job: &CopyJob) -> std::io::Result<()> {
+    if let Some(destDir) = job.destination.parent() {
+        fs::create_dir_all(destDir)?;
+    }
+    fs::copy(&job.source, &job.destination)?;
+
+    if let Some(number) = job.track_number {
+        WriteTrackNumber(&job.destination, number, job.track_total.unwrap_or(0)).map_err(|e| io::Error::other(format!("could not tag track number: {}", e)))?;
+    }
+
+    Ok(())
 }
 
-// This copies the track to folders
-// RETURNS: None this is one of the terminal functions
-fn CopyTrackToFolder(outputRoot: &Path, folderName: &str, srcPath: &Path) -> std::io::Result<()> {
-    // Clean folder name by removing .txt
-    let folder = folderName.replace(".txt", "");
-    
-    // Build destination directory and create it
-    let destDir = outputRoot.join(&folder);
-    fs::create_dir_all(&destDir)?;
+// This names the copied file, optionally prefixed with its position in the playlist
+// e.g. "007 - Artist - Title.mp3", falling back to the original name when the row has no title
+// RETURNS: Destination file name
+fn PlaylistFileName(entry: &PlaylistTrack, srcPath: &Path, options: &RunOptions) -> String {
+    let original = srcPath.file_name().unwrap().to_string_lossy().to_string();
+    if !options.number_tracks {
+        return original;
+    }
 
-    // Build destination file path
-    let filename = srcPath.file_name().unwrap();
-    let mut destPath = destDir;
-    destPath.push(filename);
+    let extension = srcPath.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let name = match (entry.Get(Column::Artist), entry.Get(Column::Title)) {
+        (Some(artist), Some(title)) => format!("{} - {}{}", artist, title, extension),
+        (None, Some(title)) => format!("{}{}", title, extension),
+        _ => original,
+    };
+
+    return format!("{} - {}", entry.PaddedPosition(), SanitiseFileName(&name));
+}
+
+// This writes the playlist position into the copied file's track number tag
+// RETURNS: Nothing, modifies the copied file
+fn WriteTrackNumber(destPath: &Path, number: u32, total: u32) -> anyhow::Result<()> {
+    let mut taggedFile = read_from_path(destPath)?;
+    if taggedFile.primary_tag().is_none() {
+        let tagType = taggedFile.primary_tag_type();
+        taggedFile.insert_tag(Tag::new(tagType));
+    }
 
-    // Copy file
-    fs::copy(srcPath, &destPath)?;
+    let tag = taggedFile.primary_tag_mut().unwrap();
+    tag.set_track(number);
+    if total > 0 { tag.set_track_total(total); }
+    tag.save_to_path(destPath)?;
 
     Ok(())
 }
 
-// This function creates a RekordCrates subfolder on the desktop, corresponding to genre (Fallback)
-// RETURNS: Nothing, modifies OS state
-fn Genre_CopyTrackToFolder(outputRoot: &Path, folderName: &str, srcPath: &Path) -> std::io::Result<()> {
+// This works out the RekordCrates/Unsorted subfolder a track goes in, corresponding to genre (Fallback)
+// RETURNS: Destination path
+fn GenreDestination(outputRoot: &Path, folderName: &str, srcPath: &Path) -> PathBuf {
     // Clean folder name by removing .txt
     let folder = folderName.replace(".txt", "");
-    let destDir = outputRoot.join("Unsorted").join(&folder);
-
-    // Create directory structure
-    fs::create_dir_all(&destDir)?;
+    outputRoot.join("Unsorted").join(&folder).join(srcPath.file_name().unwrap())
+}
 
-    // Build destination filepath
-    let filename = srcPath.file_name().unwrap();
-    let destPath = destDir.join(filename);
+// This plans a matched track into the folder of every playlist it appears in
+// A track listed twice in the same playlist is still only copied once into that folder,
+// and with M3U-only output it is copied once in total
+// RETURNS: Number of playlists the track was placed into, copies and placements are added to the plan
+fn PlanTrackToPlaylists(outputRoot: &Path, track: &Track, entries: &[PlaylistTrack], reason: &str, options: &RunOptions,
+    plan: &mut CopyPlan) -> usize {
+    let path = track.path.as_path();
+    let mut copied = Vec::<(&str, PathBuf)>::new();
+
+    for entry in entries {
+        let existing = if options.playlist_output == PlaylistOutput::M3u { copied.first() } 
+            else { copied.iter().find(|(playlist, _)| *playlist == entry.playlist) };
+
+        let destination = match existing {
+            Some((_, dest)) => dest.clone(),
+            None => {
+                let shared = options.playlist_output == PlaylistOutput::M3u;
+                let numbered = !shared && options.write_track_numbers;
+                let job = CopyJob {
+                    source: path.to_path_buf(),
+                    destination: if shared { SharedDestination(outputRoot, path) } else { PlaylistDestination(outputRoot, entry, path, options) },
+                    reason: reason.to_string(),
+                    track_number: numbered.then_some(entry.position as u32),
+                    track_total: numbered.then_some(entry.playlist_length as u32),
+                };
+
+                let artist = track.artist.as_deref().or(entry.Get(Column::Artist));
+                let Some(destination) = plan.AddJob(job, artist) else { continue; };
+                destination
+            }
+        };
 
-    // Copy file
-    fs::copy(srcPath, &destPath)?;
+        if !copied.iter().any(|(playlist, _)| *playlist == entry.playlist) {
+            copied.push((&entry.playlist, destination.clone()));
+        }
+        plan.placed.push(PlacedTrack { entry: entry.clone(), destination, track: track.clone() });
+    }
 
-    Ok(())
+    return copied.len();
 }
 
 // This handles the case where track metadata can be found
-// RETURNS: Bool corresponding to success
-fn MatchByTitle(app: &Arc<Mutex<App>>, title: String, outputRoot: &Path, path: &Path, trackMap: &HashMap<String, String>) -> bool {
+// Title is combined with artist and duration so same-named tracks do not collide
+// RETURNS: The match and the number of playlist folders the track is planned into, None if not matched
+fn MatchByTitle(app: &Arc<Mutex<App>>, query: &TrackQuery, outputRoot: &Path, track: &Track, index: &TrackIndex,
+    options: &RunOptions, plan: &mut CopyPlan) -> Option<(MatchResult, usize)> {
     {
         let mut app = app.lock().unwrap();
-        app.SetCurrentFile(format!("Processing: {}", title));
+        app.SetCurrentFile(format!("Processing: {}", query.title));
     }
 
-    if let Some(folder) = trackMap.get(&title) {
-        if let Err(e) = CopyTrackToFolder(&outputRoot, folder, path) {
-            AppError(&app, format!("Failed to copy {}: {}", path.display(), e));    
+    let result = FindPlaylistEntries(index, query, &options.matching)?;
+    let playlists = PlanTrackToPlaylists(outputRoot, track, &result.entries, result.rule.Describe(), options, plan);
+    if playlists == 0 { return None; }
+
+    return Some((result, playlists));
+}
+
+// This finds the playlist rows the user has matched a track to by hand before
+// RETURNS: The rows, empty if the track has no usable override
+fn OverrideEntries(app: &Arc<Mutex<App>>, overrides: &Overrides, moved: &[&MatchOverride], trackMap: &TrackMap, root: &Path,
+    path: &Path) -> Vec<PlaylistTrack> {
+    let mut entries = Vec::new();
+    for over in overrides.Lookup(root, path, moved) {
+        match ResolveOverride(trackMap, over) {
+            Some(entry) => entries.push(entry),
+            None => AppError(app, format!("Override for {} points at {} / {}, which is not in the playlists",
+                path.display(), over.playlist, over.title)),
         }
-        else {
-            return true;
+    }
+    return entries;
+}
+
+// This places a track by its overrides, ahead of any tag or filename matching
+// RETURNS: The match and the number of playlist folders the track is planned into, None if nothing was planned
+fn MatchByOverride(entries: Vec<PlaylistTrack>, outputRoot: &Path, track: &Track, options: &RunOptions,
+    plan: &mut CopyPlan) -> Option<(MatchResult, usize)> {
+    let result = MatchResult::New(entries, MatchRule::Override, 1.0, true);
+    let playlists = PlanTrackToPlaylists(outputRoot, track, &result.entries, result.rule.Describe(), options, plan);
+    if playlists == 0 { return None; }
+
+    return Some((result, playlists));
+}
+
+// This handles the case where no usable metadata can be found and an attempt is made to match the filename
+// RETURNS: The match and the number of playlist folders the track is planned into, None if not matched
+fn MatchByFileName(outputRoot: &Path, track: &Track, index: &TrackIndex, options: &RunOptions,
+    plan: &mut CopyPlan) -> Option<(MatchResult, usize)> {
+    let stem = Some(track.Stem()).filter(|s| !s.is_empty())?;
+    let result = FindByFileName(index, stem, track.seconds, &options.matching)?;
+    let playlists = PlanTrackToPlaylists(outputRoot, track, &result.entries, result.rule.Describe(), options, plan);
+    if playlists == 0 { return None; }
+
+    return Some((result, playlists));
+}
+
+// This is the fallback, it will attempt to sort by genre, then fallback to "unknown"
+// RETURNS: Track title corresponding to track not found, the genre copy is added to the plan
+fn NoMatchFound (app: &Arc<Mutex<App>>, outputRoot: &Path, track: &Track, plan: &mut CopyPlan) -> String {
+    // Default to genre data, then to unknown genre
+    // This is to ensure ALL files get moved, organised or not
+    let genre = track.genre.as_deref().unwrap_or("Unknown Genre");
+    plan.AddJob(CopyJob {
+        source: track.path.clone(),
+        destination: GenreDestination(outputRoot, genre, &track.path),
+        reason: "genre fallback".to_string(),
+        track_number: None,
+        track_total: None,
+    }, track.artist.as_deref());
+
+    // No match found in dictionary
+    let trackTitle = Some(track.Stem()).filter(|s| !s.is_empty()).unwrap_or("Unknown filename");
+    AppError(app, format!("Failed to identify playlist for: {}", trackTitle));
+
+    return trackTitle.to_string();
+}
+
+// This builds the review entry for a track the user should check by hand
+// The rows it was matched to come first, then the closest titles in the trackMap
+// RETURNS: Review item
+fn BuildReviewItem(index: &TrackIndex, track: &Track, reason: ReviewReason, query: &str, matched: &[PlaylistTrack],
+    copies: Vec<PathBuf>) -> ReviewItem {
+    let mut candidates: Vec<(PlaylistTrack, f64)> = matched.iter().map(|entry| (entry.clone(), 1.0)).collect();
+    for (entry, score) in RankCandidates(index, query, CANDIDATE_LIMIT) {
+        if candidates.len() >= CANDIDATE_LIMIT { break; }
+        if !candidates.iter().any(|(c, _)| c.playlist == entry.playlist && c.position == entry.position) {
+            candidates.push((entry.clone(), score));
         }
-    } 
-    return false;
+    }
+
+    ReviewItem { track: track.clone(), reason, query: query.to_string(), candidates, copies }
 }
 
-// This handles the case where no metadata can be found and an attempt is made to match the filename
-// RETURNS: Bool corresponding to success
-fn MatchByFileName(app: &Arc<Mutex<App>>, stem: &str, outputRoot: &Path, path: &Path, trackMap: &HashMap<String, String>) -> bool {
-    if let Some(folder) = trackMap.get(stem) {
-        if let Err(e) = CopyTrackToFolder(&outputRoot, folder, path) {
-            AppError(&app, format!("Failed to copy {}: {}", path.display(), e));  
+// This copies a reviewed track into the playlist picked by hand and removes the copies it replaces
+// RETURNS: Nothing, it modifies the output folders
+fn AssignReviewedTrack(app: &Arc<Mutex<App>>, track: &Track, entry: PlaylistTrack, desktop: &str, options: &RunOptions) {
+    let outputRoot = Path::new(desktop).join("RekordCrates");
+    let mut plan = CopyPlan::New(options.collision_policy);
+
+    // Files the run already wrote keep their names, the new copy is settled around them
+    if let Some(session) = app.lock().unwrap().review.as_ref() {
+        for placed in &session.placed {
+            plan.Claim(&placed.destination, &placed.track.path);
         }
-        else {
-            return true;
+    }
+
+    let placedCount = PlanTrackToPlaylists(&outputRoot, track, std::slice::from_ref(&entry), "manual assignment", options, &mut plan);
+    if let Some(collision) = plan.collisions.first() {
+        AppError(app, format!("Destination collision: {}", collision.Describe()));
+    }
+    if placedCount == 0 {
+        return;
+    }
+    for job in &plan.jobs {
+        if let Err(e) = CopyTrack(job) {
+            AppError(app, format!("Failed to copy {} to {}: {}", job.source.display(), job.destination.display(), e));
+            return;
         }
     }
 
-    return false;
+    let (stale, root) = {
+        let mut appGuard = app.lock().unwrap();
+        match appGuard.review.as_mut() {
+            Some(session) => (session.Assign(plan.placed), session.source_root.clone()),
+            None => return,
+        }
+    };
+
+    // Remembered so the next run places the track without asking
+    // A file that cannot be parsed is left alone rather than replaced by this one entry
+    match Overrides::Load(&options.overrides_path) {
+        Ok(mut overrides) => {
+            overrides.Add(&root, &track.path, &entry);
+            if let Err(e) = overrides.Save(&options.overrides_path) {
+                AppError(app, format!("Failed to save {}: {}", options.overrides_path.display(), e));
+            }
+        }
+        Err(e) => AppError(app, format!("Not saving the assignment, could not read {}: {}", options.overrides_path.display(), e)),
+    }
+
+    for copy in stale {
+        if let Err(e) = fs::remove_file(&copy) {
+            AppError(app, format!("Failed to remove {}: {}", copy.display(), e));
+        }
+    }
 }
 
-// This is the fallback, it will attempt to sort by genre, then fallback to "unknown"
-// RETURNS: Track title corresponding to track not found
-fn NoMatchFound (app: &Arc<Mutex<App>>, outputRoot: &Path, path: &Path) -> String{
-    // Default to genre data
-    if let Ok(Some(genre)) = ExtractGenreFromPath(path) {
-        if let Err(e) = Genre_CopyTrackToFolder(&outputRoot, &genre, path) {
-            AppError(&app, format!("Failed to copy {}: {}", path.display(), e)); 
+// This writes the M3U8 playlists and rekordbox.xml the flags asked for
+// RETURNS: Nothing, errors are reported to the app
+fn WritePlaylistFiles(app: &Arc<Mutex<App>>, deskPath: &str, options: &RunOptions, placed: &[PlacedTrack]) {
+    if options.playlist_output != PlaylistOutput::Folders {
+        let outputRoot = Path::new(deskPath).join("RekordCrates");
+        if let Err(e) = WriteM3uPlaylists(&outputRoot, placed) {
+            AppError(app, format!("Failed to write M3U8 playlists: {}", e));
         }
     }
 
-    // No match found in dictionary
-    let trackTitle = path.file_stem().and_then(|s| s.to_str()).unwrap_or("Unknown filename");
-    AppError(&app, format!("Failed to identify playlist for: {}", trackTitle.to_string()));
+    if options.rekordbox_xml {
+        let xmlPath = Path::new(deskPath).join("RekordCrates").join("rekordbox.xml");
+        if let Err(e) = WriteRekordboxXml(&xmlPath, placed) {
+            AppError(app, format!("Failed to write rekordbox.xml: {}", e));
+        }
+    }
+}
 
-    return trackTitle.to_string();
+// This reads every track's tags on a pool of reader threads
+// RETURNS: One Track per path, in the same order
+fn ScanTracks(app: &Arc<Mutex<App>>, paths: &[PathBuf], options: &RunOptions) -> Vec<Track> {
+    let total = paths.len().max(1) as f64;
+    let scanned = AtomicUsize::new(0);
+
+    RunPool(options.readers, paths, |path| {
+        let track = Track::Read(path).unwrap_or_else(|_| Track::Untagged(path));
+
+        // The first half of the progress bar is tag reading
+        let done = scanned.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut app = app.lock().unwrap();
+        app.UpdateProgress(done as f64 / total * 0.5);
+        app.SetCurrentFile(format!("Reading tags: {}", path.display()));
+        track
+    })
+}
+
+// This carries out a plan on a pool of writer threads, one source file at a time per writer
+// RETURNS: Destinations that could not be written
+fn CopyPlannedTracks(app: &Arc<Mutex<App>>, plan: &CopyPlan, options: &RunOptions) -> Vec<PathBuf> {
+    let files: Vec<&[CopyJob]> = plan.jobs.chunk_by(|a, b| a.source == b.source).collect();
+    let total = files.len().max(1) as f64;
+    let copied = AtomicUsize::new(0);
+
+    let failures = RunPool(options.writers, &files, |jobs| {
+        let mut failed = Vec::new();
+        for job in jobs.iter() {
+            if let Err(e) = CopyTrack(job) {
+                AppError(app, format!("Failed to copy {} to {}: {}", job.source.display(), job.destination.display(), e));
+                failed.push(job.destination.clone());
+            }
+        }
+
+        // The second half of the progress bar is copying
+        let done = copied.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut app = app.lock().unwrap();
+        app.UpdateProgress(0.5 + done as f64 / total * 0.5);
+        app.files_cleared += 1;
+        app.SetCurrentFile(format!("Copying: {}", jobs[0].source.display()));
+        failed
+    });
+
+    failures.into_iter().flatten().collect()
 }
 
 // This copies the files to their respective folders
+// Tags are read in parallel, matching is planned in order, then the copies run in parallel
 // RETURNS: Nothing, this is the final function
-fn MoveAllMp3(trackMap: &HashMap<String, String>, root: &str, deskPath: &str,
+fn MoveAllMp3(trackMap: &TrackMap, root: &Path, deskPath: &str, options: &RunOptions,
     app: Arc<Mutex<App>>) -> io::Result<()> {
     // UX Debug information
     let mut tracksNotMatched = 0;
     let mut tracksMatched = 0;
+    let mut multiPlaylistTracks = 0;
+    let mut report = MatchReport::default();
+    let mut plan = CopyPlan::New(options.collision_policy);
     let mut unsorted = Vec::<String>::new();
-    CreatePlaylistsFolder(deskPath);
+    let mut unmatched = Vec::<PathBuf>::new();
+    let mut review = Vec::<ReviewItem>::new();
+    let index = TrackIndex::New(trackMap);
+    let outputRoot = Path::new(deskPath).join("RekordCrates");
+
+    // Hand-made matches from earlier runs win over everything else
+    let overrides = Overrides::Load(&options.overrides_path).unwrap_or_else(|e| {
+        AppError(&app, format!("Could not read {}: {}", options.overrides_path.display(), e));
+        Overrides::default()
+    });
 
-    // Collect all MP3 files into a vec
-    let entries: Vec<_> = WalkDir::new(root).into_iter().filter_map(Result::ok)
-        .filter(|e| e.file_type().is_file() && e.path().extension().map_or(false, |ext| ext.eq_ignore_ascii_case("mp3"))).collect();
-   
-    let totalEntries = entries.len() as f64;
+    // Collect all audio files into a vec, counted per format for the summary
+    let paths: Vec<PathBuf> = WalkDir::new(root).into_iter().filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && IsAudioFile(e.path()))
+        .map(|e| e.into_path())
+        .collect();
+    let mut formats = HashMap::<&'static str, (usize, usize)>::new();
     
     {
         let mut app = app.lock().unwrap();
-        app.files_total = entries.len();
+        app.files_total = paths.len();
+        app.files_cleared = 0;
     }
 
-    // Iterate through all .mp3 files in Contents
-    for (idx, entry) in entries.iter().enumerate() {
-        let path = entry.path();
-        let outputRoot = Path::new(deskPath).join("RekordCrates");
-
-        // I don't think this needs to be a function yet, it is just ugly code
-        {
-            let mut app = app.lock().unwrap();
-            app.UpdateProgress((idx as f64 + 1.0) / totalEntries);
-            app.files_cleared += 1;
-        }
-
-        // Extract title and compare against dictionary
-        if let Ok(Some(title)) = ExtractTitleFromPath(path) {
-            let success = MatchByTitle(&app, title, &outputRoot, path, trackMap);
-            if success {
-                tracksMatched += 1;
-                continue;
+    // Tags and audio properties are read once, everything below works from the Track
+    let tracks = ScanTracks(&app, &paths, options);
+
+    // Overrides are looked up on the reader pool too, a moved file may have to be hashed
+    let moved = overrides.Moved(root);
+    let overrideEntries = RunPool(options.readers, &tracks, |track| OverrideEntries(&app, &overrides, &moved, trackMap, root, &track.path));
+
+    // Iterate through all audio files in Contents
+    for (track, overridden) in tracks.iter().zip(overrideEntries) {
+        let path = track.path.as_path();
+        let format = AudioFormat(path).unwrap_or("Other");
+        formats.entry(format).or_default().0 += 1;
+        let stem = track.Stem();
+
+        // Extract title and compare against dictionary, search by filename instead (sometimes the way)
+        let query = track.Query().map(|mut query| {
+            // A title tag without an artist can still borrow the artist from an "Artist - Title" file name
+            if query.artist.is_none() {
+                query.artist = ParseFileName(stem, &options.matching.filename_patterns).and_then(|parsed| parsed.artist);
             }
+            query
+        });
+        let jobsBefore = plan.jobs.len();
+        let matched = if !overridden.is_empty() {
+            MatchByOverride(overridden, &outputRoot, track, options, &mut plan)
+        }
+        else if let Some(query) = &query {
+            // Broken tags get a second chance through the file name
+            MatchByTitle(&app, query, &outputRoot, track, &index, options, &mut plan)
+                .or_else(|| MatchByFileName(&outputRoot, track, &index, options, &mut plan))
         }
-        // Search by filename instead (sometimes the way)
-        else if let Some(stem) = path.file_stem().and_then(|s| s.to_str()){
-            let success = MatchByFileName(&app, stem, &outputRoot, path, trackMap);
-            if success {
-                tracksMatched += 1;
-                continue;
+        else {
+            MatchByFileName(&outputRoot, track, &index, options, &mut plan)
+        };
+        let searchText = query.as_ref().map(|q| q.title.as_str()).unwrap_or(stem);
+
+        if let Some((result, playlists)) = matched {
+            if result.rule == MatchRule::Fuzzy {
+                let mut app = app.lock().unwrap();
+                app.SetCurrentFile(format!("Fuzzy matched {} to {} ({:.0}% similar)",
+                    path.display(), result.entries[0].Title(), result.similarity * 100.0));
             }
+            tracksMatched += 1;
+            formats.entry(format).or_default().1 += 1;
+            if playlists > 1 { multiPlaylistTracks += 1; }
+            report.Record(path, &result, &options.matching);
+
+            let reason = if result.ambiguous { Some(ReviewReason::Ambiguous) }
+                else if result.confidence < options.matching.review_threshold { Some(ReviewReason::LowConfidence(result.confidence)) }
+                else { None };
+            if let Some(reason) = reason {
+                let copies = plan.jobs[jobsBefore..].iter().map(|job| job.destination.clone()).collect();
+                review.push(BuildReviewItem(&index, track, reason, searchText, &result.entries, copies));
+            }
+            continue;
         }
 
-        let trackTitle = NoMatchFound(&app, &outputRoot, path);
+        let trackTitle = NoMatchFound(&app, &outputRoot, track, &mut plan);
         unsorted.push(trackTitle.to_string());                    
+        unmatched.push(path.to_path_buf());
+        report.RecordFailure(path);
+        let copies = plan.jobs[jobsBefore..].iter().map(|job| job.destination.clone()).collect();
+        review.push(BuildReviewItem(&index, track, ReviewReason::NotMatched, searchText, &[], copies));
         tracksNotMatched += 1;
     }
 
+    // A dry run stops here and shows what would have been written
+    if options.dry_run {
+        let preview = PlanPreview::New(plan.jobs, unmatched, plan.collisions);
+        preview.WriteTable(Path::new("DryRun.txt"))?;
+        preview.WriteJson(Path::new("DryRun.json"))?;
+
+        let mut app = app.lock().unwrap();
+        app.UpdateProgress(1.0);
+        app.SetStatusMessage(format!("Dry run: {} copies planned ({} bytes), {} unmatched, {} collisions. Formats: {}. See DryRun.txt and DryRun.json.",
+            preview.copies.len(), preview.total_bytes, preview.unmatched.len(), preview.collisions.len(), FormatSummary(&formats)));
+        return Ok(());
+    }
+
+    // Collisions are listed next to the matches that need a second look
+    report.collisions = plan.collisions.clone();
+
+    // --on-collision fail stops before anything is written
+    if options.collision_policy == CollisionPolicy::Fail && !plan.collisions.is_empty() {
+        report.WriteTo(Path::new("MatchReport.txt"))?;
+        return Err(io::Error::other(format!("{} destination collisions, nothing was copied. See MatchReport.txt.", plan.collisions.len())));
+    }
+
+    // `rekordscratch plan` also stops here, the plan file is carried out later by `rekordscratch apply`
+    if let Some(planPath) = &options.plan_file {
+        let copies = plan.jobs.len();
+        let planFile = PlanFile {
+            playlist_output: options.playlist_output,
+            rekordbox_xml: options.rekordbox_xml,
+            ..PlanFile::New(root, Path::new(deskPath), &plan.jobs, &plan.placed, unmatched, plan.collisions)
+        };
+        planFile.Save(planPath)?;
+
+        let mut app = app.lock().unwrap();
+        app.UpdateProgress(1.0);
+        app.SetStatusMessage(format!("Plan written to {}: {} copies, {} unmatched, {} collisions. Formats: {}.",
+            planPath.display(), copies, planFile.unmatched.len(), planFile.collisions.len(), FormatSummary(&formats)));
+        return Ok(());
+    }
+
+    // Copies run once everything is planned, placements whose copy failed are left out of playlist files
+    CreatePlaylistsFolder(deskPath);
+    let failed = CopyPlannedTracks(&app, &plan, options);
+    plan.placed.retain(|p| !failed.contains(&p.destination));
+    {
+        let mut app = app.lock().unwrap();
+        app.files_cleared = app.files_total;
+        app.UpdateProgress(1.0);
+    }
+
+    // Playlist files are written last so every placement is known
+    WritePlaylistFiles(&app, deskPath, options, &plan.placed);
+
     AppError(&app, format!("{} tracks not matched.", tracksNotMatched));
-    AppStatus(&app, format!("{} tracks matches successfully.", tracksMatched));
+    AppStatus(&app, format!("{} tracks matches successfully, {} of them in more than one playlist. {} ambiguous and {} low confidence matches, {} destination collisions listed in MatchReport.txt. Formats: {}.",
+        tracksMatched, multiPlaylistTracks, report.ambiguous.len(), report.low_confidence.len(), report.collisions.len(), FormatSummary(&formats)));
+
+    // Matches to double-check before a gig, kept apart from the outright failures
+    if let Err(e) = report.WriteTo(Path::new("MatchReport.txt")) {
+        AppError(&app, format!("Failed to write MatchReport.txt: {}", e));
+    }
 
     // Write all unsorted tracks to an external txt for user review
     let mut file = File::create("NotMatched.txt").expect("Error creating output file");
@@ -360,6 +784,77 @@ fn MoveAllMp3(trackMap: &HashMap<String, String>, root: &str, deskPath: &str,
         writeln!(file, "{}", line).expect("Failed to write to file.");
     }
 
+    // Anything worth a second look is handed to the review screen
+    if !review.is_empty() {
+        let mut app = app.lock().unwrap();
+        app.review = Some(ReviewSession::New(review, plan.placed, root.to_path_buf()));
+    }
+
+    Ok(())
+}
+
+// This carries out a plan file written by `rekordscratch plan`, edited by hand or not
+// Playlist rows are looked up again by name, a copy whose rows are gone is still made but left out of playlist files
+// RETURNS: Nothing, errors if the plan cannot be read or two files are planned onto one destination
+fn ApplyPlan(trackMap: &TrackMap, planPath: &Path, options: &RunOptions, app: Arc<Mutex<App>>) -> io::Result<()> {
+    let planFile = PlanFile::Load(planPath)
+        .map_err(|e| io::Error::new(e.kind(), format!("could not read plan {}: {}", planPath.display(), e)))?;
+    let outputRoot = planFile.output_root.to_string_lossy().to_string();
+    let applyOptions = RunOptions { playlist_output: planFile.playlist_output, rekordbox_xml: planFile.rekordbox_xml, ..options.clone() };
+    let writesPlaylists = applyOptions.playlist_output != PlaylistOutput::Folders || applyOptions.rekordbox_xml;
+
+    // Copies of one source stay together so a single writer handles each file
+    let mut copies = planFile.copies;
+    copies.sort_by(|a, b| a.job.source.cmp(&b.job.source));
+    let mut sources: Vec<PathBuf> = copies.iter().map(|copy| copy.job.source.clone()).collect();
+    sources.dedup();
+
+    {
+        let mut app = app.lock().unwrap();
+        app.files_total = sources.len();
+        app.files_cleared = 0;
+    }
+
+    // Tags are only needed for the playlist files
+    let tracks: HashMap<PathBuf, Track> = if writesPlaylists {
+        ScanTracks(&app, &sources, options).into_iter().map(|track| (track.path.clone(), track)).collect()
+    } else { HashMap::new() };
+
+    // The plan says exactly where everything goes, so nothing is renamed here
+    let mut plan = CopyPlan::New(CollisionPolicy::Fail);
+    let mut missingRows = 0;
+    for copy in copies {
+        let destination = copy.job.destination.clone();
+        let track = tracks.get(&copy.job.source).cloned().unwrap_or_else(|| Track::Untagged(&copy.job.source));
+        if plan.AddJob(copy.job, None).is_none() { continue; }
+
+        for row in &copy.playlists {
+            match ResolveRow(trackMap, &row.playlist, &row.title, Some(row.position)) {
+                Some(entry) => plan.placed.push(PlacedTrack { entry, destination: destination.clone(), track: track.clone() }),
+                None => missingRows += 1,
+            }
+        }
+    }
+
+    // Two files written to one path would leave whichever finished last, so nothing is copied
+    if let Some(collision) = plan.collisions.first() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} and {} are both planned to {}, give one of them another destination in {}",
+            collision.claimed_by.display(), collision.source.display(), collision.destination.display(), planPath.display())));
+    }
+
+    CreatePlaylistsFolder(&outputRoot);
+    let failed = CopyPlannedTracks(&app, &plan, &applyOptions);
+    plan.placed.retain(|p| !failed.contains(&p.destination));
+    {
+        let mut app = app.lock().unwrap();
+        app.files_cleared = app.files_total;
+        app.UpdateProgress(1.0);
+    }
+
+    WritePlaylistFiles(&app, &outputRoot, &applyOptions, &plan.placed);
+    AppStatus(&app, format!("Plan applied: {} of {} copies made. {} playlist rows in the plan were not found in the playlists.",
+        plan.jobs.len() - failed.len(), plan.jobs.len(), missingRows));
+
     Ok(())
 }
 
@@ -372,9 +867,147 @@ fn MoveAllMp3(trackMap: &HashMap<String, String>, root: &str, deskPath: &str,
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
-    /// Playlists.txt path (-t or --target)
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Playlists folder (.txt, .m3u, .m3u8, .csv) or rekordbox.xml path (-t or --target)
     #[arg(short = 't', long = "target")]
     target: Option<String>,
+
+    /// Recover from this directory (USB backup, mounted image, network copy) instead of a detected drive (--source)
+    #[arg(long = "source")]
+    source: Option<PathBuf>,
+
+    /// Accept a --source without the Rekordbox Contents/PIONEER folders
+    #[arg(long = "skip-marker-check", requires = "source")]
+    skip_marker_check: bool,
+
+    /// Prefix copied files with their playlist position, e.g. "007 - Artist - Title.mp3"
+    #[arg(long = "number-tracks")]
+    number_tracks: bool,
+
+    /// Write the playlist position into the track number tag of copied files
+    #[arg(long = "write-track-numbers")]
+    write_track_numbers: bool,
+
+    /// Rebuild playlists as folders, as .m3u8 files, or both (--playlist-output)
+    #[arg(long = "playlist-output", value_enum, default_value_t = PlaylistOutput::Folders)]
+    playlist_output: PlaylistOutput,
+
+    /// Seconds a file may differ from a playlist's Time column and still match (--duration-tolerance)
+    #[arg(long = "duration-tolerance", default_value_t = 3)]
+    duration_tolerance: u64,
+
+    /// Lowest title similarity (0.0 - 1.0) for a fuzzy match, 1.0 turns fuzzy matching off (--fuzzy-threshold)
+    #[arg(long = "fuzzy-threshold", default_value_t = 0.85)]
+    fuzzy_threshold: f64,
+
+    /// Matches below this confidence (0.0 - 1.0) are listed for review in MatchReport.txt (--review-threshold)
+    #[arg(long = "review-threshold", default_value_t = 0.8)]
+    review_threshold: f64,
+
+    /// Pattern for untagged file names, e.g. "{track} - {artist} - {title}", repeat to try several in order (--filename-pattern)
+    #[arg(long = "filename-pattern", value_parser = FileNamePattern::Parse)]
+    filename_patterns: Vec<FileNamePattern>,
+
+    /// Match everything but copy nothing, the plan is written to DryRun.txt and DryRun.json (--dry-run)
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+
+    /// What to do when two files would get the same name in one folder (--on-collision)
+    #[arg(long = "on-collision", value_enum, default_value_t = CollisionPolicy::Counter)]
+    on_collision: CollisionPolicy,
+
+    /// Number of threads reading tags (--readers)
+    #[arg(long = "readers", default_value_t = 4)]
+    readers: usize,
+
+    /// Number of threads copying files (--writers)
+    #[arg(long = "writers", default_value_t = 2)]
+    writers: usize,
+
+    /// Hand-made matches file, defaults to RekordScratchOverrides.json next to the playlists folder (--overrides)
+    #[arg(long = "overrides")]
+    overrides: Option<PathBuf>,
+
+    /// Also write RekordCrates/rekordbox.xml so the recovered library can be imported back into Rekordbox
+    #[arg(long = "rekordbox-xml")]
+    rekordbox_xml: bool,
+}
+
+// Runs without the terminal UI, for reviewing a recovery before anything is copied
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Match everything and write the planned copies to an editable plan file, nothing is copied
+    Plan {
+        /// Where to write the plan
+        #[arg(default_value = PLAN_FILE_NAME)]
+        file: PathBuf,
+    },
+
+    /// Copy exactly what a plan file lists and write its playlist files
+    Apply {
+        /// Plan written by `plan`, edited or not
+        #[arg(default_value = PLAN_FILE_NAME)]
+        file: PathBuf,
+    },
+}
+
+// How recovered playlists are written to RekordCrates
+#[derive(clap::ValueEnum, serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum PlaylistOutput {
+    // One folder per playlist holding copies of its tracks
+    #[default]
+    Folders,
+    // Every track copied once into Tracks/, one .m3u8 per playlist
+    M3u,
+    // Playlist folders plus an .m3u8 next to each
+    Both,
+}
+
+// Settings that shape how a run writes its output, taken from the flags
+#[derive(Clone, Debug, Default)]
+struct RunOptions {
+    number_tracks: bool,
+    write_track_numbers: bool,
+    playlist_output: PlaylistOutput,
+    rekordbox_xml: bool,
+    matching: MatchSettings,
+    overrides_path: PathBuf,
+    readers: usize,
+    writers: usize,
+    dry_run: bool,
+    collision_policy: CollisionPolicy,
+    // Set by `rekordscratch plan`, the run writes this plan file instead of copying
+    plan_file: Option<PathBuf>,
+}
+
+impl RunOptions {
+    fn FromArgs(args: &Args, txtPath: &str) -> Self {
+        Self {
+            number_tracks: args.number_tracks,
+            write_track_numbers: args.write_track_numbers,
+            playlist_output: args.playlist_output,
+            rekordbox_xml: args.rekordbox_xml,
+            matching: MatchSettings {
+                duration_tolerance: args.duration_tolerance,
+                fuzzy_threshold: args.fuzzy_threshold,
+                review_threshold: args.review_threshold,
+                filename_patterns: if args.filename_patterns.is_empty() { DefaultFileNamePatterns() }
+                    else { args.filename_patterns.clone() },
+            },
+            overrides_path: args.overrides.clone().unwrap_or_else(|| DefaultOverridesPath(txtPath)),
+            readers: args.readers.max(1),
+            writers: args.writers.max(1),
+            dry_run: args.dry_run,
+            collision_policy: args.on_collision,
+            plan_file: match &args.command {
+                Some(Command::Plan { file }) => Some(file.clone()),
+                _ => None,
+            },
+        }
+    }
 }
 
 // This sets the location of the playlists.txt files
@@ -397,20 +1030,34 @@ fn SetTxtFileLocation() -> String {
 // NOTE: Main_ prefix indicates it is ONLY to be called from within the main function
 // If it has to be called from elsewhere it should be refactored
 
-// This checks for removable drives again
-// RETURNS: Drive name, and it changes app state
-fn Main_RemovableDriveCheck(app: Arc<Mutex<App>>) -> String {
-    let letter = DetectRemovableDrives();
-    if !letter.is_empty() {
-        let originPath = format!("{}:\\", letter);
-        if let Ok(mut app) = app.lock() {
-            app.SetDriveLetter(format!("{}:\\", letter));
-            app.SetDriveStatus(true);
-            return originPath;
-        }
+// This checks for removable drives again, or uses the --source directory when one is given
+// RETURNS: Nothing, it fills the drive list in app state
+fn Main_RemovableDriveCheck(app: Arc<Mutex<App>>, args: &Args) {
+    if let Some(source) = &args.source {
+        Main_SetSourceDrive(app, source, args.skip_marker_check);
+        return;
     }
 
-    return String::new();
+    let drives = DetectRemovableDrives();
+    if let Ok(mut app) = app.lock() {
+        app.SetDrives(drives);
+    }
+}
+
+// This validates the --source directory and makes it the only drive on offer
+// RETURNS: Nothing, it changes app state
+fn Main_SetSourceDrive(app: Arc<Mutex<App>>, source: &Path, skipMarkerCheck: bool) {
+    let mut app = app.lock().unwrap();
+    match DetectSourceDrive(source, skipMarkerCheck) {
+        Ok(drive) => {
+            app.SetDrives(vec![drive]);
+            app.SetStatusMessage(format!("Using source {}", source.display()));
+        }
+        Err(e) => {
+            app.SetDrives(Vec::new());
+            app.SetError(e.to_string());
+        }
+    }
 }
 
 // This checks for the desktop
@@ -428,8 +1075,8 @@ fn Main_SetDesktopState(app: Arc<Mutex<App>>) -> String {
 
 // This sets the playlists path
 // RETURNS: Playlists path, and it alters app state
-fn Main_SetPlaylistsPath(app: Arc<Mutex<App>>, args: Args) -> String {
-    let txtPath = args.target.unwrap_or_else(|| SetTxtFileLocation());
+fn Main_SetPlaylistsPath(app: Arc<Mutex<App>>, args: &Args) -> String {
+    let txtPath = args.target.clone().unwrap_or_else(SetTxtFileLocation);
 
     if !txtPath.is_empty() {
         if let Ok(mut app) = app.lock() {
@@ -440,49 +1087,79 @@ fn Main_SetPlaylistsPath(app: Arc<Mutex<App>>, args: Args) -> String {
 }
 
 // This is the secondary way to scan for drives
-// RETURNS: Drive letter string
-fn Main_RescanForDrives(app: Arc<Mutex<App>>) -> String {
-    let letter = DetectRemovableDrives();
+// RETURNS: Nothing, it refreshes the drive list in app state
+fn Main_RescanForDrives(app: Arc<Mutex<App>>, args: &Args) {
+    // An explicit source is re-validated rather than replaced by whatever USB is plugged in
+    if let Some(source) = &args.source {
+        Main_SetSourceDrive(app, source, args.skip_marker_check);
+        return;
+    }
+
+    let drives = DetectRemovableDrives();
     let mut app = app.lock().unwrap();
     
-    if letter.is_empty() {
+    if drives.is_empty() {
         app.SetError("No drive detected.");
-        app.SetDriveLetter("N/A");
-        app.SetDriveStatus(false);
     } 
     else {
-        let letter = format!("{}:\\", letter);
-        app.SetDriveLetter(letter);
-        app.SetDriveStatus(true);
-        app.SetStatusMessage("Drive detected.");
+        app.SetStatusMessage(format!("{} drive(s) detected.", drives.len()));
     }
+    app.SetDrives(drives);
+}
 
-    return letter;
+// This counts the tracks on every listed drive, one thread per drive, as a big stick takes a while to walk
+// RETURNS: Nothing, the drive list is updated as each count finishes
+fn Main_CountDriveTracks(app: Arc<Mutex<App>>) {
+    let mountPoints: Vec<PathBuf> = app.lock().unwrap().drives.iter().map(|d| d.mount_point.clone()).collect();
+
+    for mountPoint in mountPoints {
+        let appClone = Arc::clone(&app);
+        std::thread::spawn(move || {
+            let count = CountTracksOnDrive(&mountPoint);
+            let mut app = appClone.lock().unwrap();
+            if let Some(drive) = app.drives.iter_mut().find(|d| d.mount_point == mountPoint) {
+                drive.track_count = Some(count);
+            }
+        });
+    }
 }
 
 // This starts the copyMp3 function
 // RETURNS: Nothing
-fn Main_StartMp3(app: &Arc<Mutex<App>>, origin: String, desktop: String, map: HashMap<String, String>) {
+fn Main_StartMp3(app: &Arc<Mutex<App>>, origin: PathBuf, desktop: String, map: TrackMap, options: RunOptions) {
     // Mutex clones
-    let appClone = Arc::clone(&app);
+    let appClone = Arc::clone(app);
     let desktopClone = desktop.clone();
     let originClone = origin.clone(); 
 
+    let dryRun = options.dry_run;
+
     std::thread::spawn (move || {
         {
             let mut app = appClone.lock().unwrap();
             app.is_mp3_copying = true;
-            app.SetStatusMessage("Copying files...");
+            app.SetStatusMessage(if dryRun { "Planning dry run..." } else { "Copying files..." });
             app.UpdateProgress(0.0);
         }
         
         {
-            if let Err(e) = MoveAllMp3(&map, &originClone, &desktopClone, appClone.clone()) {
+            if let Err(e) = MoveAllMp3(&map, &originClone, &desktopClone, &options, appClone.clone()) {
                 let mut app = appClone.lock().unwrap();
-                app.SetError(&format!("Error: {}", e));
+                app.SetError(format!("Error: {}", e));
             } else {
                 let mut app = appClone.lock().unwrap();
-                app.SetStatusMessage("All files copied over!");
+                if dryRun {
+                    let summary = app.status_message.clone();
+                    app.SetStatusMessage(format!("Dry run finished, nothing was copied. {}", summary));
+                    app.is_mp3_copying = false;
+                    return;
+                }
+
+                // The run summary stays on screen underneath
+                let summary = app.status_message.clone();
+                let toReview = app.review.as_ref().map(|session| session.items.len()).unwrap_or(0);
+                if toReview > 0 { app.SetStatusMessage(format!("All files copied over! {} tracks to review. {}", toReview, summary)); }
+                else { app.SetStatusMessage(format!("All files copied over! {}", summary)); }
             }
         }
         let mut app = appClone.lock().unwrap();
@@ -491,14 +1168,183 @@ fn Main_StartMp3(app: &Arc<Mutex<App>>, origin: String, desktop: String, map: Ha
 
 }
 
+// This handles a keypress while the review screen is open
+// RETURNS: Whether the key was used by the review screen
+fn Main_ReviewKey(app: &Arc<Mutex<App>>, key: KeyCode, trackMap: &TrackMap, desktop: &str, options: &RunOptions) -> bool {
+    let assignment = {
+        let mut appGuard = app.lock().unwrap();
+        let copying = appGuard.is_mp3_copying;
+        let Some(session) = appGuard.review.as_mut() else { return false; };
+
+        // A hand assignment is still copying, the screen waits for it
+        if copying {
+            return true;
+        }
+
+        // While searching every key goes to the search box
+        if let Some(search) = session.search.as_mut() {
+            match key {
+                KeyCode::Char(c) => search.push(c),
+                KeyCode::Backspace => { search.pop(); }
+                KeyCode::Esc => session.search = None,
+                KeyCode::Enter => {
+                    let text = session.search.take().unwrap_or_default();
+                    let index = TrackIndex::New(trackMap);
+                    let candidates = RankCandidates(&index, &text, CANDIDATE_LIMIT).into_iter()
+                        .map(|(entry, score)| (entry.clone(), score)).collect();
+                    session.SetCandidates(candidates);
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        match key {
+            // Quitting closes the review first so the playlist files match what is on disk
+            KeyCode::Char('q') => session.current = session.items.len(),
+            KeyCode::Up => session.SelectPrevious(),
+            KeyCode::Down => session.SelectNext(),
+            KeyCode::Char('/') => session.search = Some(String::new()),
+            KeyCode::Char('n') | KeyCode::Right => session.Skip(),
+            KeyCode::Esc => session.current = session.items.len(),
+            _ => {}
+        }
+
+        if key == KeyCode::Enter {
+            session.Current().zip(session.SelectedCandidate()).map(|(item, entry)| (item.track.clone(), entry.clone()))
+        } else { None }
+    };
+
+    if let Some((track, entry)) = assignment {
+        Main_ReviewAssign(app, track, entry, desktop, options);
+        return true;
+    }
+
+    let finished = app.lock().unwrap().review.as_ref().is_some_and(|session| session.IsFinished());
+    if finished {
+        Main_FinishReview(app, desktop, options);
+    }
+
+    // Q is passed on so the main loop exits once the review is closed
+    return key != KeyCode::Char('q');
+}
+
+// This copies a reviewed track into the playlist picked by hand on a worker thread, so the screen stays responsive
+// RETURNS: Nothing, keys are held back until the copy is done
+fn Main_ReviewAssign(app: &Arc<Mutex<App>>, track: Track, entry: PlaylistTrack, desktop: &str, options: &RunOptions) {
+    let appClone = Arc::clone(app);
+    let desktopClone = desktop.to_string();
+    let optionsClone = options.clone();
+
+    app.lock().unwrap().is_mp3_copying = true;
+    std::thread::spawn(move || {
+        AssignReviewedTrack(&appClone, &track, entry, &desktopClone, &optionsClone);
+
+        // The last item closes the review here, nothing else would until the next key
+        let finished = {
+            let mut app = appClone.lock().unwrap();
+            app.is_mp3_copying = false;
+            app.review.as_ref().is_some_and(|session| session.IsFinished())
+        };
+        if finished {
+            Main_FinishReview(&appClone, &desktopClone, &optionsClone);
+        }
+    });
+}
+
+// This closes the review screen and rewrites playlist files with the hand picked placements
+// RETURNS: Nothing, it changes app state
+fn Main_FinishReview(app: &Arc<Mutex<App>>, desktop: &str, options: &RunOptions) {
+    let Some(session) = app.lock().unwrap().review.take() else { return; };
+
+    WritePlaylistFiles(app, desktop, options, &session.placed);
+    AppStatus(app, format!("Review finished: {} assigned by hand, {} skipped.", session.assigned, session.skipped));
+}
+
+// This reads the playlists folder or rekordbox.xml into the track map
+// RETURNS: Nothing, it fills the map and changes app state
+fn Main_LoadPlaylists(app: &Arc<Mutex<App>>, map: &mut TrackMap, txtPath: &str) {
+    let mut appGuard = app.lock().unwrap();
+
+    // Either a folder of playlist files (.txt, .m3u, .m3u8, .csv) or a rekordbox.xml collection export
+    let buildResult = if IsRekordboxXml(txtPath) { BuildMapFromXml(map, Path::new(txtPath)) }
+        else { BuildMapFromPlaylists(map, txtPath) };
+
+    match buildResult {
+        Ok(warnings) => {
+            for warning in warnings {
+                appGuard.AddWarning(warning);
+            }
+            appGuard.SetTrackMapStatus(true);
+            appGuard.SetPlaylistStatus(true);
+        }
+        Err(e) => appGuard.SetError(format!("Could not read playlists from {}: {}", txtPath, e)),
+    }
+}
+
+// This runs `plan` or `apply` without the terminal UI, printing the outcome
+// RETURNS: Nothing, errors are printed and returned
+fn Main_RunCommand(args: &Args, command: &Command) -> io::Result<()> {
+    let app = Arc::new(Mutex::new(App::new()));
+    let txtPath = Main_SetPlaylistsPath(app.clone(), args);
+    let mut map = TrackMap::new();
+    if !txtPath.is_empty() {
+        Main_LoadPlaylists(&app, &mut map, &txtPath);
+    }
+    let options = RunOptions::FromArgs(args, &txtPath);
+
+    let result = match command {
+        Command::Plan { .. } => {
+            let desktopPath = Main_SetDesktopState(app.clone());
+            Main_RemovableDriveCheck(app.clone(), args);
+            let (origin, mapCreated, drives) = {
+                let appGuard = app.lock().unwrap();
+                let drives: Vec<String> = appGuard.drives.iter().map(|d| d.mount_point.display().to_string()).collect();
+                (appGuard.drive_path.clone(), appGuard.track_map_created, drives)
+            };
+
+            // Without the UI there is no way to pick a stick, so guessing one is not an option
+            match origin {
+                Some(_) if drives.len() > 1 => Err(io::Error::other(format!("{} drives detected ({}), pick one with --source",
+                    drives.len(), drives.join(", ")))),
+                Some(_) if !mapCreated => Err(io::Error::other("no playlists to match against, point -t at them")),
+                Some(origin) => MoveAllMp3(&map, &origin, &desktopPath, &options, app.clone()),
+                None => Err(io::Error::other("no drive detected, plug one in or pass --source")),
+            }
+        }
+        Command::Apply { file } => ApplyPlan(&map, file, &options, app.clone()),
+    };
+
+    let appGuard = app.lock().unwrap();
+    for warning in &appGuard.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Some(error) = appGuard.error_message.as_ref().filter(|e| !e.is_empty()) {
+        eprintln!("{}", error);
+    }
+    if result.is_ok() {
+        println!("{}", appGuard.status_message);
+    }
+    result
+}
+
 // ---------------------------------------------------------------------------------------------------------------------
 
 fn main() -> std::io::Result<()> {
-    enable_raw_mode()?;
-
     // Flags
     let args = Args::parse();
 
+    // plan and apply run without the terminal UI
+    if let Some(command) = &args.command {
+        if let Err(e) = Main_RunCommand(&args, command) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    enable_raw_mode()?;
+
     // Program state
     let trackMap = Arc::new(Mutex::new(HashMap::new()));
     let mut errorTicks = 0;
@@ -511,17 +1357,14 @@ fn main() -> std::io::Result<()> {
     let app = Arc::new(Mutex::new(App::new()));
 
     // Check for paths and drives
-    let mut originPath = Main_RemovableDriveCheck(app.clone());
+    Main_RemovableDriveCheck(app.clone(), &args);
+    Main_CountDriveTracks(app.clone());
     let desktopPath = Main_SetDesktopState(app.clone());
-    let txtPath = Main_SetPlaylistsPath(app.clone(), args);
+    let txtPath = Main_SetPlaylistsPath(app.clone(), &args);
     
     { 
         let mut map = trackMap.lock().unwrap();
-        let _ = BuildMapFromTxt(&mut map, &txtPath);
-
-        let mut appGuard = app.lock().unwrap();
-        appGuard.SetTrackMapStatus(true);
-        appGuard.SetPlaylistStatus(true);
+        Main_LoadPlaylists(&app, &mut map, &txtPath);
     }
 
     // Ratatui mainloop
@@ -535,18 +1378,35 @@ fn main() -> std::io::Result<()> {
         // Keypress inputs
         if event::poll(Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
+                // The review screen takes the keyboard until it is closed
+                {
+                    let map = trackMap.lock().unwrap();
+                    if Main_ReviewKey(&app, key.code, &map, &desktopPath, &RunOptions::FromArgs(&args, &txtPath)) { continue; }
+                }
+
                 match key.code {
                     // Exit
                     KeyCode::Char('q') => break,
                 
                     // Rescan drive (s for scan)
                     KeyCode::Char('s') => {
-                        originPath = Main_RescanForDrives(app.clone());
+                        Main_RescanForDrives(app.clone(), &args);
+                        Main_CountDriveTracks(app.clone());
+                    }
+
+                    // Pick which connected drive the run should use
+                    KeyCode::Up => {
+                        let mut appGuard = app.lock().unwrap();
+                        if !appGuard.is_mp3_copying { appGuard.SelectPreviousDrive(); }
+                    }
+                    KeyCode::Down => {
+                        let mut appGuard = app.lock().unwrap();
+                        if !appGuard.is_mp3_copying { appGuard.SelectNextDrive(); }
                     }
 
-                    // Main logic, r for run
-                    KeyCode::Char('r') => { 
-                        {
+                    // Main logic, r for run, d for a dry run that copies nothing
+                    KeyCode::Char(runKey @ ('r' | 'd')) => { 
+                        let origin = {
                             let mut appGuard = app.lock().unwrap();
                             if appGuard.is_mp3_copying { continue; }
 
@@ -555,11 +1415,15 @@ fn main() -> std::io::Result<()> {
                                 appGuard.error_message = Some("Not all flags green! Refer to warning lights".to_string());
                                 continue;
                             }
-                        }
+                            let Some(origin) = appGuard.drive_path.clone() else { continue; };
+                            origin
+                        };
                         
                         let trackMapClone = Arc::clone(&trackMap);
                         let map = trackMapClone.lock().unwrap();
-                        Main_StartMp3(&app.clone(), originPath.clone(), desktopPath.clone(), map.clone());
+                        let mut options = RunOptions::FromArgs(&args, &txtPath);
+                        options.dry_run |= runKey == 'd';
+                        Main_StartMp3(&app.clone(), origin, desktopPath.clone(), map.clone(), options);
                     },
 
                     _ => continue
@@ -580,3 +1444,89 @@ fn main() -> std::io::Result<()> {
     execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::ScratchDir;
+
+    fn Job(source: &Path, destination: &str) -> CopyJob {
+        CopyJob { source: source.to_path_buf(), destination: PathBuf::from(destination), reason: "test".to_string(), track_number: None, track_total: None }
+    }
+
+    // Two source files named track.mp3 in different album folders, with the given contents
+    fn Sources(a: &[u8], b: &[u8]) -> (ScratchDir, PathBuf, PathBuf) {
+        let usb = ScratchDir::New("sources");
+        let (a, b) = (usb.Write("A/track.mp3", a), usb.Write("B/track.mp3", b));
+        (usb, a, b)
+    }
+
+    #[test]
+    fn CounterRenamesLaterFiles() {
+        let mut plan = CopyPlan::New(CollisionPolicy::Counter);
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/A/track.mp3"), "/out/Set/track.mp3"), None), Some(PathBuf::from("/out/Set/track.mp3")));
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/B/track.mp3"), "/out/Set/track.mp3"), None), Some(PathBuf::from("/out/Set/track (2).mp3")));
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/C/track.mp3"), "/out/Set/track.mp3"), None), Some(PathBuf::from("/out/Set/track (3).mp3")));
+        assert_eq!(plan.jobs.len(), 3);
+        assert_eq!(plan.collisions.len(), 2);
+    }
+
+    #[test]
+    fn SameSourceIsNotACollision() {
+        let mut plan = CopyPlan::New(CollisionPolicy::Fail);
+        plan.AddJob(Job(Path::new("/usb/A/track.mp3"), "/out/Set/track.mp3"), None);
+        assert!(plan.AddJob(Job(Path::new("/usb/A/track.mp3"), "/out/Set/track.mp3"), None).is_some());
+        assert_eq!(plan.jobs.len(), 1);
+        assert!(plan.collisions.is_empty());
+    }
+
+    #[test]
+    fn DestinationsDifferingInCaseCollide() {
+        let mut plan = CopyPlan::New(CollisionPolicy::Counter);
+        plan.AddJob(Job(Path::new("/usb/A/Track.mp3"), "/out/Set/Track.mp3"), None);
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/B/track.mp3"), "/out/Set/track.mp3"), None), Some(PathBuf::from("/out/Set/track (2).mp3")));
+        assert_eq!(plan.collisions.len(), 1);
+    }
+
+    #[test]
+    fn ArtistSuffixFallsBackToCounter() {
+        let mut plan = CopyPlan::New(CollisionPolicy::Artist);
+        plan.AddJob(Job(Path::new("/usb/A/track.mp3"), "/out/Set/track.mp3"), None);
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/B/track.mp3"), "/out/Set/track.mp3"), Some("AC/DC")), Some(PathBuf::from("/out/Set/track (AC_DC).mp3")));
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/C/track.mp3"), "/out/Set/track.mp3"), Some("AC/DC")), Some(PathBuf::from("/out/Set/track (2).mp3")));
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/D/track.mp3"), "/out/Set/track.mp3"), None), Some(PathBuf::from("/out/Set/track (3).mp3")));
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/E/track.mp3"), "/out/Set/track.mp3"), Some(" ")), Some(PathBuf::from("/out/Set/track (4).mp3")));
+    }
+
+    #[test]
+    fn SkipIdenticalSharesDuplicatesOnly() {
+        let (_usb, a, b) = Sources(b"same", b"same");
+        let mut plan = CopyPlan::New(CollisionPolicy::SkipIdentical);
+        plan.AddJob(Job(&a, "/out/Set/track.mp3"), None);
+        assert_eq!(plan.AddJob(Job(&b, "/out/Set/track.mp3"), None), Some(PathBuf::from("/out/Set/track.mp3")));
+        assert_eq!(plan.jobs.len(), 1);
+        assert_eq!(plan.collisions[0].resolution, Resolution::SharedIdentical);
+
+        let (_usb, a, b) = Sources(b"one", b"two");
+        let mut plan = CopyPlan::New(CollisionPolicy::SkipIdentical);
+        plan.AddJob(Job(&a, "/out/Set/track.mp3"), None);
+        assert_eq!(plan.AddJob(Job(&b, "/out/Set/track.mp3"), None), Some(PathBuf::from("/out/Set/track (2).mp3")));
+        assert_eq!(plan.jobs.len(), 2);
+    }
+
+    #[test]
+    fn FailRecordsAndDropsTheCopy() {
+        let mut plan = CopyPlan::New(CollisionPolicy::Fail);
+        plan.AddJob(Job(Path::new("/usb/A/track.mp3"), "/out/Set/track.mp3"), None);
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/B/track.mp3"), "/out/Set/track.mp3"), None), None);
+        assert_eq!(plan.jobs.len(), 1);
+        assert_eq!(plan.collisions[0].resolution, Resolution::Failed);
+    }
+
+    #[test]
+    fn ClaimedDestinationsAreAvoided() {
+        let mut plan = CopyPlan::New(CollisionPolicy::Counter);
+        plan.Claim(Path::new("/out/Set/track.mp3"), Path::new("/usb/A/track.mp3"));
+        assert_eq!(plan.AddJob(Job(Path::new("/usb/B/track.mp3"), "/out/Set/track.mp3"), None), Some(PathBuf::from("/out/Set/track (2).mp3")));
+    }
+}
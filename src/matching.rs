@@ -0,0 +1,857 @@
+use crate::playlist::{Column, PlaylistTrack, TrackMap};
+use crate::plan::Collision;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::fs::File;
+use std::io;
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Match types
+
+// What we know about a file on the USB when looking it up in the trackMap
+#[derive(Clone, Debug, Default)]
+pub struct TrackQuery {
+    pub title: String,
+    pub artist: Option<String>,
+    pub seconds: Option<u64>,
+}
+
+// Which rule placed a track, strongest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchRule {
+    TitleArtistDuration,
+    TitleArtist,
+    TitleDuration,
+    TitleOnly,
+    FileName,
+    // Title only matched after normalisation and similarity scoring
+    Fuzzy,
+    // Picked by hand, from the overrides file
+    Override,
+}
+
+impl MatchRule {
+    pub fn Describe(&self) -> &'static str {
+        match self {
+            MatchRule::TitleArtistDuration => "exact title + artist + duration",
+            MatchRule::TitleArtist => "exact title + artist",
+            MatchRule::TitleDuration => "exact title + duration",
+            MatchRule::TitleOnly => "exact title",
+            MatchRule::FileName => "filename stem",
+            MatchRule::Fuzzy => "fuzzy title",
+            MatchRule::Override => "manual override",
+        }
+    }
+
+    // How much a match by this rule is trusted before similarity is taken into account
+    fn BaseConfidence(&self, artistAgreed: bool) -> f64 {
+        match self {
+            MatchRule::TitleArtistDuration => 1.0,
+            MatchRule::TitleArtist => 0.9,
+            MatchRule::TitleDuration => 0.75,
+            MatchRule::TitleOnly => 0.6,
+            MatchRule::FileName => 0.5,
+            MatchRule::Fuzzy => if artistAgreed { 0.9 } else { 0.6 },
+            MatchRule::Override => 1.0,
+        }
+    }
+}
+
+// The playlist rows a file was matched to, and how
+#[derive(Clone, Debug)]
+pub struct MatchResult {
+    pub entries: Vec<PlaylistTrack>,
+    pub rule: MatchRule,
+    // 1.0 for exact and normalised title matches, the similarity score for fuzzy ones
+    pub similarity: f64,
+    // 0.0 - 1.0, how sure we are the rows describe this file
+    pub confidence: f64,
+    // The rows describe more than one distinct track (e.g. two "Intro"s and the file has no artist tag)
+    pub ambiguous: bool,
+}
+
+impl MatchResult {
+    pub fn New(entries: Vec<PlaylistTrack>, rule: MatchRule, similarity: f64, artistAgreed: bool) -> Self {
+        Self {
+            confidence: rule.BaseConfidence(artistAgreed) * similarity,
+            ambiguous: DistinctTracks(&entries) > 1,
+            entries,
+            rule,
+            similarity,
+        }
+    }
+
+    // This narrows the rows down after the fact, e.g. by mix name
+    // RETURNS: The narrowed match, or None if no row is left
+    fn Retain(mut self, keep: impl Fn(&PlaylistTrack) -> bool) -> Option<Self> {
+        self.entries.retain(keep);
+        self.ambiguous = DistinctTracks(&self.entries) > 1;
+        if self.entries.is_empty() { None } else { Some(self) }
+    }
+}
+
+// Rows are the same track when their normalised title and artist agree
+fn DistinctTracks(entries: &[PlaylistTrack]) -> usize {
+    let mut identities: Vec<(String, String)> = entries.iter()
+        .map(|e| (NormaliseTitle(e.Title()), NormaliseTitle(e.Get(Column::Artist).unwrap_or_default())))
+        .collect();
+    identities.sort();
+    identities.dedup();
+    identities.len()
+}
+
+// Knobs for the matcher, taken from the flags
+#[derive(Clone, Debug)]
+pub struct MatchSettings {
+    // Largest difference in seconds between a file and a playlist Time column that still counts as the same track
+    pub duration_tolerance: u64,
+    // Lowest similarity (0.0 - 1.0) a normalised title may have to a playlist title to be matched, 1.0 turns fuzzy matching off
+    pub fuzzy_threshold: f64,
+    // Matches below this confidence are listed for review in the match report
+    pub review_threshold: f64,
+    // Tried in order on the names of files with missing or broken tags
+    pub filename_patterns: Vec<FileNamePattern>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self { duration_tolerance: 3, fuzzy_threshold: 0.85, review_threshold: 0.8, filename_patterns: DefaultFileNamePatterns() }
+    }
+}
+
+// The trackMap plus its titles in normalised form, built once per run
+pub struct TrackIndex<'a> {
+    map: &'a TrackMap,
+    normalised: HashMap<String, Vec<&'a str>>,
+}
+
+impl<'a> TrackIndex<'a> {
+    pub fn New(map: &'a TrackMap) -> Self {
+        let mut normalised: HashMap<String, Vec<&'a str>> = HashMap::new();
+        for title in map.keys() {
+            normalised.entry(NormaliseTitle(title)).or_default().push(title.as_str());
+        }
+        Self { map, normalised }
+    }
+
+    // This looks a title up exactly, then normalised, then by similarity
+    // A step whose rows are all ruled out by usable (another artist's "Intro") falls through to the next one
+    // RETURNS: Every playlist row under the best title(s), the similarity they were found with and whether similarity scoring found them
+    fn Lookup(&self, title: &str, settings: &MatchSettings, usable: impl Fn(&[&'a PlaylistTrack]) -> bool) -> Option<(Vec<&'a PlaylistTrack>, f64, bool)> {
+        if let Some(entries) = self.map.get(title.trim()) {
+            let entries: Vec<&'a PlaylistTrack> = entries.iter().collect();
+            if usable(&entries) {
+                return Some((entries, 1.0, false));
+            }
+        }
+
+        let key = NormaliseTitle(title);
+        if key.is_empty() {
+            return None;
+        }
+        if let Some(titles) = self.normalised.get(&key) {
+            let entries = self.EntriesFor(titles);
+            if usable(&entries) {
+                return Some((entries, 1.0, false));
+            }
+        }
+
+        if settings.fuzzy_threshold >= 1.0 {
+            return None;
+        }
+
+        // Keep every title sharing the best score, ties are settled by artist and duration later
+        let mut best: Option<(f64, Vec<&'a str>)> = None;
+        for (candidate, titles) in &self.normalised {
+            let score = Similarity(&key, candidate);
+            if score < settings.fuzzy_threshold || !usable(&self.EntriesFor(titles)) { continue; }
+
+            match &mut best {
+                Some((bestScore, bestTitles)) if (score - *bestScore).abs() < f64::EPSILON => bestTitles.extend(titles),
+                Some((bestScore, _)) if score < *bestScore => {}
+                _ => best = Some((score, titles.clone())),
+            }
+        }
+
+        best.map(|(score, titles)| (self.EntriesFor(&titles), score, true))
+    }
+
+    fn EntriesFor(&self, titles: &[&'a str]) -> Vec<&'a PlaylistTrack> {
+        titles.iter().filter_map(|title| self.map.get(*title)).flatten().collect()
+    }
+}
+
+// How a single attribute of a playlist row compares to the file
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Agreement {
+    Match,
+    Mismatch,
+    Unknown,
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Matching
+
+// This finds the playlist rows a tagged file belongs to
+// Rows sharing the title are narrowed down by artist, then by duration when the playlist has a Time column.
+// A row that contradicts the file on either is a different track with the same name ("Intro", "Original Mix")
+// RETURNS: The rows and the rule that picked them, or None if nothing fits
+pub fn FindPlaylistEntries(index: &TrackIndex, query: &TrackQuery, settings: &MatchSettings) -> Option<MatchResult> {
+    let usable = |entries: &[&PlaylistTrack]| entries.iter()
+        .any(|entry| CompareArtist(entry, query) != Agreement::Mismatch && CompareDuration(entry, query, settings) != Agreement::Mismatch);
+    let (candidates, similarity, fuzzy) = index.Lookup(&query.title, settings, usable)?;
+
+    let checked: Vec<(&PlaylistTrack, Agreement, Agreement)> = candidates.into_iter()
+        .map(|entry| (entry, CompareArtist(entry, query), CompareDuration(entry, query, settings)))
+        .collect();
+
+    // Composite match: artist agrees and duration does not disagree
+    let composite: Vec<&(&PlaylistTrack, Agreement, Agreement)> = checked.iter()
+        .filter(|(_, artist, duration)| *artist == Agreement::Match && *duration != Agreement::Mismatch)
+        .collect();
+    if !composite.is_empty() {
+        let rule = if fuzzy { MatchRule::Fuzzy }
+            else if composite.iter().all(|(_, _, duration)| *duration == Agreement::Match) { MatchRule::TitleArtistDuration }
+            else { MatchRule::TitleArtist };
+        let entries = composite.iter().map(|(entry, _, _)| (*entry).clone()).collect();
+        return Some(MatchResult::New(entries, rule, similarity, true));
+    }
+
+    // Lower confidence fallback: nothing to compare the artist against, but nothing contradicts the title either
+    let fallback: Vec<&(&PlaylistTrack, Agreement, Agreement)> = checked.iter()
+        .filter(|(_, artist, duration)| *artist == Agreement::Unknown && *duration != Agreement::Mismatch)
+        .collect();
+    if !fallback.is_empty() {
+        let rule = if fuzzy { MatchRule::Fuzzy }
+            else if fallback.iter().all(|(_, _, duration)| *duration == Agreement::Match) { MatchRule::TitleDuration }
+            else { MatchRule::TitleOnly };
+        let entries = fallback.iter().map(|(entry, _, _)| (*entry).clone()).collect();
+        return Some(MatchResult::New(entries, rule, similarity, false));
+    }
+
+    None
+}
+
+// This finds the playlist rows for an untagged file by its file name
+// Each filename pattern is tried in turn, the whole stem is compared to titles last
+// RETURNS: The rows, or None if no title resembles the file stem
+pub fn FindByFileName(index: &TrackIndex, stem: &str, seconds: Option<u64>, settings: &MatchSettings) -> Option<MatchResult> {
+    for pattern in &settings.filename_patterns {
+        let Some(parsed) = pattern.Apply(stem) else { continue; };
+        if let Some(result) = FindByParsedName(index, &parsed, seconds, settings) {
+            return Some(result);
+        }
+    }
+
+    let (entries, similarity, fuzzy) = index.Lookup(stem, settings, |_| true)?;
+    let rule = if fuzzy { MatchRule::Fuzzy } else { MatchRule::FileName };
+    Some(MatchResult::New(entries.into_iter().cloned().collect(), rule, similarity, false))
+}
+
+// This ranks every playlist row against free text, for picking a match by hand
+// The text is compared to the title and to "artist title", a row containing the whole text scores at least 0.9
+// RETURNS: Up to limit rows, best first
+pub fn RankCandidates<'a>(index: &TrackIndex<'a>, text: &str, limit: usize) -> Vec<(&'a PlaylistTrack, f64)> {
+    let key = NormaliseTitle(text);
+    if key.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ranked: Vec<(&'a PlaylistTrack, f64)> = index.map.values().flatten()
+        .map(|entry| {
+            let title = NormaliseTitle(entry.Title());
+            let full = NormaliseTitle(&format!("{} {}", entry.Get(Column::Artist).unwrap_or_default(), entry.Title()));
+            let mut score = Similarity(&key, &title).max(Similarity(&key, &full));
+            if full.contains(&key) { score = score.max(0.9); }
+            (entry, score)
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1)
+        .then_with(|| a.0.playlist.cmp(&b.0.playlist))
+        .then_with(|| a.0.position.cmp(&b.0.position)));
+    ranked.truncate(limit);
+    ranked
+}
+
+// This matches the pieces of a file name the same way as tags
+// "Title (Extended Mix)" is looked up whole first, then as "Title" against rows with a matching Mix Name column
+// RETURNS: The rows, or None if nothing fits
+fn FindByParsedName(index: &TrackIndex, parsed: &ParsedFileName, seconds: Option<u64>, settings: &MatchSettings) -> Option<MatchResult> {
+    let query = |title: String| TrackQuery { title, artist: parsed.artist.clone(), seconds };
+
+    let Some(mix) = &parsed.mix else {
+        return FindPlaylistEntries(index, &query(parsed.title.clone()), settings);
+    };
+
+    if let Some(result) = FindPlaylistEntries(index, &query(format!("{} ({})", parsed.title, mix)), settings) {
+        return Some(result);
+    }
+
+    let mixKey = NormaliseTitle(mix);
+    FindPlaylistEntries(index, &query(parsed.title.clone()), settings)?
+        .Retain(|entry| entry.Get(Column::MixName).is_none_or(|rowMix| NormaliseTitle(rowMix) == mixKey))
+}
+
+// Artists agree when one contains the other, so "Artist" still matches "Artist feat. Guest"
+fn CompareArtist(entry: &PlaylistTrack, query: &TrackQuery) -> Agreement {
+    let (Some(rowArtist), Some(fileArtist)) = (entry.Get(Column::Artist), query.artist.as_deref()) else {
+        return Agreement::Unknown;
+    };
+
+    let rowArtist = NormaliseTitle(rowArtist);
+    let fileArtist = NormaliseTitle(fileArtist);
+    if rowArtist.is_empty() || fileArtist.is_empty() {
+        return Agreement::Unknown;
+    }
+
+    if rowArtist.contains(&fileArtist) || fileArtist.contains(&rowArtist) { Agreement::Match } else { Agreement::Mismatch }
+}
+
+fn CompareDuration(entry: &PlaylistTrack, query: &TrackQuery, settings: &MatchSettings) -> Agreement {
+    let (Some(rowSeconds), Some(fileSeconds)) = (entry.Get(Column::Time).and_then(ParseTime), query.seconds) else {
+        return Agreement::Unknown;
+    };
+
+    if rowSeconds.abs_diff(fileSeconds) <= settings.duration_tolerance { Agreement::Match } else { Agreement::Mismatch }
+}
+
+// This reads a Time cell: "5:32", "05:32", "1:05:32" or plain seconds
+// RETURNS: Seconds, or None if the cell is not a duration
+pub fn ParseTime(time: &str) -> Option<u64> {
+    let mut seconds = 0;
+    for part in time.trim().split(':') {
+        seconds = seconds * 60 + part.trim().parse::<u64>().ok()?;
+    }
+    Some(seconds)
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Normalisation and similarity
+
+// Mix names Rekordbox shows in the title that say nothing about which track it is
+const NEUTRAL_MIX_NAMES: &[&str] = &["original mix", "original"];
+
+// This reduces a title or artist to a comparable form:
+// no accents, lower case, straight quotes, "ft."/"featuring" -> "feat", no neutral mix suffix, no punctuation, single spaces
+// RETURNS: Normalised text
+pub fn NormaliseTitle(text: &str) -> String {
+    // Decompose so accents become separate combining marks, then drop them
+    let mut normalised: String = text.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+        .replace(['\u{2018}', '\u{2019}', '\u{201B}', '`', '\u{00B4}'], "'")
+        .replace(['\u{201C}', '\u{201D}', '\u{201E}'], "\"");
+
+    // Strip "(Original Mix)", "[Original Mix]" and "- Original Mix" style suffixes
+    for mix in NEUTRAL_MIX_NAMES {
+        for suffix in [format!("({})", mix), format!("[{}]", mix), format!("- {}", mix)] {
+            if let Some(stripped) = normalised.trim_end().strip_suffix(&suffix) {
+                normalised = stripped.to_string();
+            }
+        }
+    }
+
+    let words: Vec<String> = normalised
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(|w| match w {
+            "ft" | "featuring" | "feat" => "feat".to_string(),
+            _ => w.trim_matches('\'').to_string(),
+        })
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    words.join(" ")
+}
+
+// Highest score two different titles can get, so only identical titles reach 1.0
+const MAX_FUZZY_SIMILARITY: f64 = 0.99;
+
+// This scores two normalised titles by edit distance over their words
+// Each word counts as one symbol, and words within one typo of each other count as equal
+// RETURNS: 1.0 for identical titles, at most MAX_FUZZY_SIMILARITY for different ones, down to 0.0 for nothing in common
+pub fn Similarity(a: &str, b: &str) -> f64 {
+    let aWords: Vec<&str> = a.split(' ').collect();
+    let bWords: Vec<&str> = b.split(' ').collect();
+    let longest = aWords.len().max(bWords.len());
+    if a == b || longest == 0 {
+        return 1.0;
+    }
+
+    let distance = EditDistance(&aWords, &bWords, |x, y| WordsAlike(x, y));
+
+    // Word level distance decides whether the words are the same, the character level score can only lower it
+    // so "love forever" stays apart from "live forever" even though they are one letter apart
+    let wordScore = 1.0 - distance as f64 / longest as f64;
+    let aChars: Vec<char> = a.chars().collect();
+    let bChars: Vec<char> = b.chars().collect();
+    let charScore = 1.0 - EditDistance(&aChars, &bChars, |x, y| x == y) as f64 / aChars.len().max(bChars.len()).max(1) as f64;
+
+    wordScore.min(charScore).min(MAX_FUZZY_SIMILARITY)
+}
+
+// Longer words one typo apart ("remixx", "remix") are treated as the same word
+// Short words ("love", "live") and numbers ("2018", "2019") are too easily different words to allow a typo
+fn WordsAlike(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+    if a.chars().chain(b.chars()).any(|c| c.is_numeric()) {
+        return false;
+    }
+    let aChars: Vec<char> = a.chars().collect();
+    let bChars: Vec<char> = b.chars().collect();
+    aChars.len() > 4 && bChars.len() > 4 && EditDistance(&aChars, &bChars, |x, y| x == y) <= 1
+}
+
+// Levenshtein distance over any sequence, with a custom notion of equal symbols
+fn EditDistance<T>(a: &[T], b: &[T], same: impl Fn(&T, &T) -> bool) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + if same(x, y) { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Filename patterns
+// For files whose tags are missing or broken, e.g. "{track} - {artist} - {title}" reads "03 - Artist - Title (Extended Mix)"
+
+const DEFAULT_FILENAME_PATTERNS: &[&str] = &["{track} - {artist} - {title}", "{track}. {artist} - {title}", "{artist} - {title}"];
+
+pub fn DefaultFileNamePatterns() -> Vec<FileNamePattern> {
+    DEFAULT_FILENAME_PATTERNS.iter().map(|p| FileNamePattern::Parse(p).unwrap()).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PatternField {
+    Artist,
+    Title,
+    Mix,
+    // Digits only, thrown away
+    Track,
+    // Anything, thrown away
+    Ignore,
+}
+
+#[derive(Clone, Debug)]
+enum PatternPart {
+    Literal(String),
+    Field(PatternField),
+}
+
+// A file name template made of {placeholders} and the literal text between them
+#[derive(Clone, Debug)]
+pub struct FileNamePattern {
+    parts: Vec<PatternPart>,
+}
+
+// What a pattern pulled out of a file name
+#[derive(Clone, Debug, Default)]
+pub struct ParsedFileName {
+    pub artist: Option<String>,
+    pub title: String,
+    pub mix: Option<String>,
+}
+
+impl FileNamePattern {
+    // This reads a pattern such as "{artist} - {title} ({mix})"
+    // Placeholders are {artist}, {title}, {mix}, {track} and {ignore}, two placeholders need text between them
+    // RETURNS: The pattern, or an error message for the flag parser
+    pub fn Parse(pattern: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = pattern;
+
+        while !rest.is_empty() {
+            let Some(open) = rest.find('{') else {
+                parts.push(PatternPart::Literal(rest.to_string()));
+                break;
+            };
+            if open > 0 {
+                parts.push(PatternPart::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..].find('}').ok_or_else(|| format!("unclosed {{ in \"{}\"", pattern))? + open;
+            let field = match &rest[open + 1..close] {
+                "artist" => PatternField::Artist,
+                "title" => PatternField::Title,
+                "mix" => PatternField::Mix,
+                "track" => PatternField::Track,
+                "ignore" => PatternField::Ignore,
+                other => return Err(format!("unknown placeholder {{{}}} in \"{}\"", other, pattern)),
+            };
+            if matches!(parts.last(), Some(PatternPart::Field(_))) {
+                return Err(format!("placeholders need text between them in \"{}\"", pattern));
+            }
+            parts.push(PatternPart::Field(field));
+            rest = &rest[close + 1..];
+        }
+
+        if !parts.iter().any(|part| matches!(part, PatternPart::Field(PatternField::Title))) {
+            return Err(format!("\"{}\" has no {{title}}", pattern));
+        }
+        Ok(Self { parts })
+    }
+
+    // This fits a file stem to the pattern, each placeholder takes text up to the next literal
+    // A trailing "(...)" or "[...]" on the title becomes the mix name when the pattern has no {mix}
+    // RETURNS: The pieces, or None if the stem does not fit
+    pub fn Apply(&self, stem: &str) -> Option<ParsedFileName> {
+        let mut parsed = ParsedFileName::default();
+        let mut rest = stem;
+
+        for (i, part) in self.parts.iter().enumerate() {
+            let field = match part {
+                PatternPart::Literal(literal) => {
+                    rest = rest.strip_prefix(literal.as_str())?;
+                    continue;
+                }
+                PatternPart::Field(field) => *field,
+            };
+
+            let value = match self.parts.get(i + 1) {
+                Some(PatternPart::Literal(next)) => {
+                    let at = rest.find(next.as_str())?;
+                    let (value, remaining) = rest.split_at(at);
+                    rest = remaining;
+                    value
+                }
+                _ => std::mem::take(&mut rest),
+            };
+
+            let value = value.trim();
+            if value.is_empty() { return None; }
+
+            match field {
+                PatternField::Artist => parsed.artist = Some(value.to_string()),
+                PatternField::Title => parsed.title = value.to_string(),
+                PatternField::Mix => parsed.mix = Some(value.to_string()),
+                PatternField::Track => if !value.chars().all(|c| c.is_ascii_digit()) { return None; },
+                PatternField::Ignore => {}
+            }
+        }
+
+        if !rest.trim().is_empty() {
+            return None;
+        }
+
+        if parsed.mix.is_none() {
+            if let Some((title, mix)) = SplitMixName(&parsed.title) {
+                parsed.title = title;
+                parsed.mix = Some(mix);
+            }
+        }
+        Some(parsed)
+    }
+}
+
+// This tries each pattern in turn on a file stem
+// RETURNS: The first fit, or None
+pub fn ParseFileName(stem: &str, patterns: &[FileNamePattern]) -> Option<ParsedFileName> {
+    patterns.iter().find_map(|pattern| pattern.Apply(stem))
+}
+
+// "Title (Extended Mix)" -> ("Title", "Extended Mix")
+fn SplitMixName(title: &str) -> Option<(String, String)> {
+    let title = title.trim_end();
+    let (open, close) = if title.ends_with(')') { ('(', ')') } else if title.ends_with(']') { ('[', ']') } else { return None; };
+    let start = title.rfind(open)?;
+    let mix = title[start + 1..title.len() - close.len_utf8()].trim();
+    let bare = title[..start].trim();
+    if mix.is_empty() || bare.is_empty() { return None; }
+    Some((bare.to_string(), mix.to_string()))
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Match report
+// Everything worth double-checking before a gig, split by why
+
+#[derive(Default)]
+pub struct MatchReport {
+    pub low_confidence: Vec<(PathBuf, MatchResult)>,
+    pub ambiguous: Vec<(PathBuf, MatchResult)>,
+    pub failures: Vec<PathBuf>,
+    // Files planned onto a name another file already had, and how each was settled
+    pub collisions: Vec<Collision>,
+}
+
+impl MatchReport {
+    // This files a match under ambiguous or low confidence if it needs a second look
+    // RETURNS: Nothing, modifies the report
+    pub fn Record(&mut self, path: &Path, result: &MatchResult, settings: &MatchSettings) {
+        if result.ambiguous {
+            self.ambiguous.push((path.to_path_buf(), result.clone()));
+        }
+        else if result.confidence < settings.review_threshold {
+            self.low_confidence.push((path.to_path_buf(), result.clone()));
+        }
+    }
+
+    pub fn RecordFailure(&mut self, path: &Path) {
+        self.failures.push(path.to_path_buf());
+    }
+
+    // This writes the report as plain text, ambiguous first since those are the likeliest wrong copies
+    // RETURNS: Nothing, writes the file
+    pub fn WriteTo(&self, reportPath: &Path) -> io::Result<()> {
+        let mut file = File::create(reportPath)?;
+
+        writeln!(file, "AMBIGUOUS MATCHES ({}) - the playlist rows describe more than one track", self.ambiguous.len())?;
+        for (path, result) in &self.ambiguous {
+            WriteReportEntry(&mut file, path, result)?;
+        }
+
+        writeln!(file)?;
+        writeln!(file, "LOW CONFIDENCE MATCHES ({})", self.low_confidence.len())?;
+        for (path, result) in &self.low_confidence {
+            WriteReportEntry(&mut file, path, result)?;
+        }
+
+        writeln!(file)?;
+        writeln!(file, "NOT MATCHED ({})", self.failures.len())?;
+        for path in &self.failures {
+            writeln!(file, "  {}", path.display())?;
+        }
+
+        writeln!(file)?;
+        writeln!(file, "DESTINATION COLLISIONS ({})", self.collisions.len())?;
+        for collision in &self.collisions {
+            writeln!(file, "  {}", collision.Describe())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn WriteReportEntry(file: &mut File, path: &Path, result: &MatchResult) -> io::Result<()> {
+    writeln!(file, "  {}", path.display())?;
+    writeln!(file, "    rule: {}, confidence: {:.0}%", result.rule.Describe(), result.confidence * 100.0)?;
+    for entry in &result.entries {
+        writeln!(file, "    -> {} #{}: {} - {}", entry.PlaylistName(), entry.position,
+            entry.Get(Column::Artist).unwrap_or("?"), entry.Title())?;
+    }
+    Ok(())
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn NormaliseTitleFoldsAccentsQuotesAndFeat() {
+        assert_eq!(NormaliseTitle("Café Del Mar"), "cafe del mar");
+        assert_eq!(NormaliseTitle("Don\u{2019}t Stop"), "don't stop");
+        assert_eq!(NormaliseTitle("Song ft. Guest"), "song feat guest");
+        assert_eq!(NormaliseTitle("Song featuring Guest"), "song feat guest");
+    }
+
+    #[test]
+    fn NormaliseTitleStripsNeutralMixNames() {
+        assert_eq!(NormaliseTitle("Song (Original Mix)"), "song");
+        assert_eq!(NormaliseTitle("Song [Original Mix]"), "song");
+        assert_eq!(NormaliseTitle("Song - Original Mix"), "song");
+        assert_eq!(NormaliseTitle("Song (Extended Mix)"), "song extended mix");
+    }
+
+    #[test]
+    fn SimilarityIsOneOnlyForIdenticalTitles() {
+        assert_eq!(Similarity("live forever", "live forever"), 1.0);
+        for (a, b) in [("love forever", "live forever"), ("anthem 2019", "anthem 2018"), ("stay", "star"), ("home", "hope"), ("remixx", "remix")] {
+            assert!(Similarity(a, b) < 1.0, "{} vs {}", a, b);
+        }
+
+        let threshold = MatchSettings::default().fuzzy_threshold;
+        assert!(Similarity("love forever", "live forever") < threshold);
+        assert!(Similarity("anthem 2019", "anthem 2018") < threshold);
+    }
+
+    #[test]
+    fn WordsAlikeAllowsOneTypoInLongWordsOnly() {
+        assert!(WordsAlike("remixx", "remix"));
+        assert!(!WordsAlike("love", "live"));
+        assert!(!WordsAlike("2019", "2018"));
+        assert!(!WordsAlike("mix2019", "mix2018"));
+    }
+
+    fn Map(rows: &[(&str, &str, &str)]) -> TrackMap {
+        let headers = vec!["Track Title".to_string(), "Artist".to_string()];
+        let columns = vec![Some(Column::Title), Some(Column::Artist)];
+        let mut map = TrackMap::new();
+        for (i, (title, artist, playlist)) in rows.iter().enumerate() {
+            let row = PlaylistTrack::FromCells(&[title.to_string(), artist.to_string()], i + 1, &headers, &columns, playlist);
+            map.entry(title.to_string()).or_default().push(row);
+        }
+        map
+    }
+
+    fn Query(title: &str, artist: &str) -> TrackQuery {
+        TrackQuery { title: title.to_string(), artist: Some(artist.to_string()), seconds: None }
+    }
+
+    #[test]
+    fn DifferentTitleIsNeverReportedAsExact() {
+        let map = Map(&[("Love Forever", "DJ X", "Set.txt"), ("Paradise Forever", "DJ X", "Set.txt")]);
+        let index = TrackIndex::New(&map);
+        let settings = MatchSettings::default();
+
+        assert!(FindPlaylistEntries(&index, &Query("Live Forever", "DJ X"), &settings).is_none());
+        assert!(FindByFileName(&index, "Live Forever", None, &settings).is_none());
+
+        let result = FindPlaylistEntries(&index, &Query("Paradize Forever", "DJ X"), &settings).unwrap();
+        assert_eq!(result.rule, MatchRule::Fuzzy);
+        assert!((result.similarity - 0.9375).abs() < 1e-9);
+        assert!((result.confidence - 0.9 * 0.9375).abs() < 1e-9);
+
+        let result = FindByFileName(&index, "Paradize Forever", None, &settings).unwrap();
+        assert_eq!(result.rule, MatchRule::Fuzzy);
+        assert!((result.confidence - 0.6 * 0.9375).abs() < 1e-9);
+    }
+
+    #[test]
+    fn OneLetterInAShortWordIsADifferentTitle() {
+        let map = Map(&[("Love Forever", "DJ X", "Set.txt"), ("Anthem 2019", "DJ X", "Set.txt")]);
+        let index = TrackIndex::New(&map);
+        assert!(FindPlaylistEntries(&index, &Query("Live Forever", "DJ X"), &MatchSettings::default()).is_none());
+        assert!(FindPlaylistEntries(&index, &Query("Anthem 2018", "DJ X"), &MatchSettings::default()).is_none());
+    }
+
+    #[test]
+    fn NormalisedTitleIsNotFuzzy() {
+        let map = Map(&[("Love Forever (Original Mix)", "DJ X", "Set.txt")]);
+        let index = TrackIndex::New(&map);
+        let result = FindPlaylistEntries(&index, &Query("love forever", "DJ X"), &MatchSettings::default()).unwrap();
+        assert_eq!(result.rule, MatchRule::TitleArtist);
+        assert_eq!(result.similarity, 1.0);
+    }
+
+    #[test]
+    fn TypoInLongWordIsMatchedAsFuzzy() {
+        let map = Map(&[("Paradise Forever", "DJ X", "Set.txt")]);
+        let index = TrackIndex::New(&map);
+        let result = FindPlaylistEntries(&index, &Query("Paradize Forever", "DJ X"), &MatchSettings::default()).unwrap();
+        assert_eq!(result.rule, MatchRule::Fuzzy);
+    }
+
+    #[test]
+    fn FuzzyThresholdOfOneTurnsFuzzyMatchingOff() {
+        let map = Map(&[("Paradise Forever", "DJ X", "Set.txt")]);
+        let index = TrackIndex::New(&map);
+        let settings = MatchSettings { fuzzy_threshold: 1.0, ..MatchSettings::default() };
+        assert!(FindPlaylistEntries(&index, &Query("Paradize Forever", "DJ X"), &settings).is_none());
+    }
+
+    #[test]
+    fn ArtistMismatchFallsThroughToNormalisedTitle() {
+        let map = Map(&[("Song", "Someone Else", "Other.txt"), ("Song (Original Mix)", "DJ X", "Set.txt")]);
+        let index = TrackIndex::New(&map);
+        let result = FindPlaylistEntries(&index, &Query("Song", "DJ X"), &MatchSettings::default()).unwrap();
+        assert_eq!(result.rule, MatchRule::TitleArtist);
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].playlist, "Set.txt");
+    }
+
+    #[test]
+    fn ArtistMismatchRejectsSameTitle() {
+        let map = Map(&[("Intro", "Someone Else", "Set.txt")]);
+        let index = TrackIndex::New(&map);
+        assert!(FindPlaylistEntries(&index, &Query("Intro", "DJ X"), &MatchSettings::default()).is_none());
+    }
+
+    fn Parsed(pattern: &str, stem: &str) -> Option<(Option<String>, String, Option<String>)> {
+        FileNamePattern::Parse(pattern).unwrap().Apply(stem).map(|p| (p.artist, p.title, p.mix))
+    }
+
+    #[test]
+    fn FileNamePatternRejectsBadPatterns() {
+        assert!(FileNamePattern::Parse("{artist} - {title}").is_ok());
+        assert!(FileNamePattern::Parse("{artist} - {name}").unwrap_err().contains("unknown placeholder"));
+        assert!(FileNamePattern::Parse("{artist} - {title").unwrap_err().contains("unclosed"));
+        assert!(FileNamePattern::Parse("{artist}{title}").unwrap_err().contains("text between"));
+        assert!(FileNamePattern::Parse("{artist} - {mix}").unwrap_err().contains("no {title}"));
+    }
+
+    #[test]
+    fn FileNamePatternSplitsFieldsAndMixName() {
+        assert_eq!(Parsed("{artist} - {title}", "DJ X - Song (Extended Mix)"),
+            Some((Some("DJ X".to_string()), "Song".to_string(), Some("Extended Mix".to_string()))));
+        assert_eq!(Parsed("{track} - {artist} - {title}", "03 - DJ X - Song"),
+            Some((Some("DJ X".to_string()), "Song".to_string(), None)));
+        assert_eq!(Parsed("{title} ({mix}) - {artist}", "Song (Dub) - DJ X"),
+            Some((Some("DJ X".to_string()), "Song".to_string(), Some("Dub".to_string()))));
+        assert_eq!(Parsed("{ignore}_{title}", "a1b2_Song [VIP]"),
+            Some((None, "Song".to_string(), Some("VIP".to_string()))));
+    }
+
+    #[test]
+    fn FileNamePatternRejectsStemsThatDoNotFit() {
+        assert_eq!(Parsed("{track} - {artist} - {title}", "DJ X - Song - Other"), None);
+        assert_eq!(Parsed("{artist} - {title}", "Song"), None);
+        assert_eq!(Parsed("{artist} - {title}", " - Song"), None);
+        assert_eq!(Parsed("{track}. {title}", "03. Song extra. bits"), Some((None, "Song extra. bits".to_string(), None)));
+    }
+
+    #[test]
+    fn DefaultPatternsTryNumberedNamesFirst() {
+        let patterns = DefaultFileNamePatterns();
+        let parsed = ParseFileName("03 - DJ X - Song", &patterns).unwrap();
+        assert_eq!((parsed.artist.as_deref(), parsed.title.as_str()), (Some("DJ X"), "Song"));
+        let parsed = ParseFileName("03. DJ X - Song", &patterns).unwrap();
+        assert_eq!((parsed.artist.as_deref(), parsed.title.as_str()), (Some("DJ X"), "Song"));
+        assert!(ParseFileName("Song", &patterns).is_none());
+    }
+
+    #[test]
+    fn ParseTimeReadsTimeCells() {
+        assert_eq!(ParseTime("5:32"), Some(332));
+        assert_eq!(ParseTime("05:32"), Some(332));
+        assert_eq!(ParseTime("1:05:32"), Some(3932));
+        assert_eq!(ParseTime(" 332 "), Some(332));
+        assert_eq!(ParseTime(""), None);
+        assert_eq!(ParseTime("5:xx"), None);
+    }
+
+    #[test]
+    fn DurationPicksBetweenSameNamedRows() {
+        let headers = vec!["Track Title".to_string(), "Artist".to_string(), "Time".to_string()];
+        let columns = vec![Some(Column::Title), Some(Column::Artist), Some(Column::Time)];
+        let mut map = TrackMap::new();
+        for (i, (time, playlist)) in [("03:00", "Short.txt"), ("07:00", "Extended.txt")].iter().enumerate() {
+            let row = PlaylistTrack::FromCells(&["Intro".to_string(), "DJ X".to_string(), time.to_string()], i + 1, &headers, &columns, playlist);
+            map.entry("Intro".to_string()).or_default().push(row);
+        }
+        let index = TrackIndex::New(&map);
+        let query = TrackQuery { title: "Intro".to_string(), artist: Some("DJ X".to_string()), seconds: Some(421) };
+
+        let result = FindPlaylistEntries(&index, &query, &MatchSettings::default()).unwrap();
+        assert_eq!(result.rule, MatchRule::TitleArtistDuration);
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].playlist, "Extended.txt");
+    }
+}
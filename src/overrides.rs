@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::Read;
+use std::fs;
+use std::io;
+use crate::playlist::{PlaylistTrack, TrackMap};
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Override file
+
+// Name of the overrides file, kept next to the playlists folder so it travels with the exports
+pub const OVERRIDES_FILE_NAME: &str = "RekordScratchOverrides.json";
+
+// One hand-made decision: this file on the USB belongs to this playlist row
+// A file in three playlists gets three of these
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchOverride {
+    // Path relative to the USB root, with "/" separators so it works from any machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    // Content hash, still finds the file after the USB folders are reorganised
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    // File size, so a file only has to be hashed when its size fits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    // Playlist name as shown in RekordCrates (folders joined with "/", no extension)
+    pub playlist: String,
+    pub title: String,
+    // Used to pick between rows with the same title in one playlist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Overrides {
+    #[serde(default)]
+    pub overrides: Vec<MatchOverride>,
+}
+
+impl Overrides {
+    // This reads the overrides file, a missing file is the same as an empty one
+    // RETURNS: Overrides, or an error if the file exists but cannot be parsed
+    pub fn Load(path: &Path) -> io::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn Save(&self, path: &Path) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, text + "\n")
+    }
+
+    // This finds the hash overrides whose file is no longer at its recorded path, worked out once per run
+    // Only these can match a file by content, every other override is found by its path
+    // RETURNS: Overrides to look up by content hash
+    pub fn Moved(&self, root: &Path) -> Vec<&MatchOverride> {
+        self.overrides.iter()
+            .filter(|o| o.hash.is_some() && o.path.as_ref().is_none_or(|path| !root.join(path).is_file()))
+            .collect()
+    }
+
+    // This finds the overrides for a file, by path first and by content hash when the path is unknown
+    // The hash reads the whole file, so it is only worked out when a moved override has the same size
+    // RETURNS: Every override for the file, empty if there are none
+    pub fn Lookup<'a>(&'a self, root: &Path, srcPath: &Path, moved: &[&'a MatchOverride]) -> Vec<&'a MatchOverride> {
+        let relative = RelativeSourcePath(root, srcPath);
+        let byPath: Vec<&MatchOverride> = self.overrides.iter().filter(|o| o.path.as_deref() == Some(relative.as_str())).collect();
+        if !byPath.is_empty() || moved.is_empty() {
+            return byPath;
+        }
+
+        let Ok(size) = fs::metadata(srcPath).map(|m| m.len()) else { return Vec::new(); };
+        let sized: Vec<&MatchOverride> = moved.iter().copied().filter(|o| o.size.is_none_or(|s| s == size)).collect();
+        if sized.is_empty() {
+            return Vec::new();
+        }
+
+        let Ok(hash) = ContentHash(srcPath) else { return Vec::new(); };
+        sized.into_iter().filter(|o| o.hash.as_deref() == Some(hash.as_str())).collect()
+    }
+
+    // This records that a file belongs to a playlist row, replacing any earlier decision for the file
+    // RETURNS: Nothing, modifies the overrides
+    pub fn Add(&mut self, root: &Path, srcPath: &Path, entry: &PlaylistTrack) {
+        let relative = RelativeSourcePath(root, srcPath);
+        let hash = ContentHash(srcPath).ok();
+        let size = fs::metadata(srcPath).map(|m| m.len()).ok();
+
+        self.overrides.retain(|o| o.path.as_deref() != Some(relative.as_str()) && (hash.is_none() || o.hash != hash));
+        self.overrides.push(MatchOverride {
+            path: Some(relative),
+            hash,
+            size,
+            playlist: entry.PlaylistName(),
+            title: entry.Title().to_string(),
+            position: Some(entry.position),
+        });
+    }
+}
+
+// This finds where the overrides file lives for a playlists folder or rekordbox.xml
+// RETURNS: Path beside the folder (or inside the xml's folder)
+pub fn DefaultOverridesPath(target: &str) -> PathBuf {
+    let target = Path::new(target.trim_end_matches(['/', '\\']));
+    let parent = target.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    parent.join(OVERRIDES_FILE_NAME)
+}
+
+// This finds the playlist rows an override points at
+// RETURNS: The row, or None when the playlists no longer have it
+pub fn ResolveOverride(trackMap: &TrackMap, over: &MatchOverride) -> Option<PlaylistTrack> {
+    ResolveRow(trackMap, &over.playlist, &over.title, over.position)
+}
+
+// This finds a playlist row by playlist name and title, the position picks between rows with the same title
+// RETURNS: The row, or None when the playlists no longer have it
+pub fn ResolveRow(trackMap: &TrackMap, playlist: &str, title: &str, position: Option<usize>) -> Option<PlaylistTrack> {
+    let rows: Vec<&PlaylistTrack> = trackMap.values().flatten()
+        .filter(|entry| entry.PlaylistName() == playlist && entry.Title() == title)
+        .collect();
+
+    rows.iter().find(|entry| Some(entry.position) == position)
+        .or_else(|| rows.first())
+        .map(|entry| (*entry).clone())
+}
+
+fn RelativeSourcePath(root: &Path, srcPath: &Path) -> String {
+    let relative = srcPath.strip_prefix(root).unwrap_or(srcPath);
+    relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+// This hashes a file's contents with 64-bit FNV-1a, stable across runs and machines
+// RETURNS: Hex string of the hash
+pub fn ContentHash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = [0u8; 64 * 1024];
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 { break; }
+        for byte in &buffer[..read] {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    Ok(format!("{:016x}", hash))
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::ScratchDir;
+
+    fn Override(path: &str, hash: Option<String>, size: Option<u64>) -> MatchOverride {
+        MatchOverride { path: Some(path.to_string()), hash, size, playlist: "Set".to_string(), title: "Song".to_string(), position: Some(1) }
+    }
+
+    fn Usb() -> ScratchDir {
+        let usb = ScratchDir::New("overrides");
+        fs::create_dir_all(usb.Path().join("Contents")).unwrap();
+        usb
+    }
+
+    #[test]
+    fn LookupFindsFileByPath() {
+        let usb = Usb();
+        let root = usb.Path();
+        fs::write(root.join("Contents/song.mp3"), b"audio").unwrap();
+        let overrides = Overrides { overrides: vec![Override("Contents/song.mp3", None, None)] };
+
+        let moved = overrides.Moved(root);
+        assert!(moved.is_empty());
+        assert_eq!(overrides.Lookup(root, &root.join("Contents/song.mp3"), &moved).len(), 1);
+        assert!(overrides.Lookup(root, &root.join("Contents/other.mp3"), &moved).is_empty());
+    }
+
+    #[test]
+    fn LookupFindsMovedFileByHash() {
+        let usb = Usb();
+        let root = usb.Path();
+        let moved = root.join("Contents/Renamed/song.mp3");
+        fs::create_dir_all(moved.parent().unwrap()).unwrap();
+        fs::write(&moved, b"audio").unwrap();
+        let hash = ContentHash(&moved).unwrap();
+
+        let overrides = Overrides { overrides: vec![Override("Contents/song.mp3", Some(hash.clone()), Some(5))] };
+        let movedOverrides = overrides.Moved(root);
+        assert_eq!(movedOverrides.len(), 1);
+        assert_eq!(overrides.Lookup(root, &moved, &movedOverrides).len(), 1);
+
+        // A different size rules the file out before it is hashed
+        let overrides = Overrides { overrides: vec![Override("Contents/song.mp3", Some(hash), Some(6))] };
+        let movedOverrides = overrides.Moved(root);
+        assert!(overrides.Lookup(root, &moved, &movedOverrides).is_empty());
+    }
+
+    #[test]
+    fn HashOverrideAtItsPathIsNotMoved() {
+        let usb = Usb();
+        let root = usb.Path();
+        fs::write(root.join("Contents/song.mp3"), b"audio").unwrap();
+        let overrides = Overrides { overrides: vec![Override("Contents/song.mp3", Some("0".to_string()), None)] };
+        assert!(overrides.Moved(root).is_empty());
+    }
+
+    #[test]
+    fn LoadRejectsBrokenJson() {
+        let usb = Usb();
+        let root = usb.Path();
+        let path = root.join(OVERRIDES_FILE_NAME);
+        fs::write(&path, "{ \"overrides\": [").unwrap();
+        assert!(Overrides::Load(&path).is_err());
+        assert!(Overrides::Load(&root.join("missing.json")).unwrap().overrides.is_empty());
+    }
+}
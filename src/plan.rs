@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::fs::File;
+use std::fs;
+use std::io;
+use crate::export::PlacedTrack;
+use crate::overrides::ContentHash;
+use crate::PlaylistOutput;
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Copy jobs
+
+// One file to write: a source track and where it is copied to
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CopyJob {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    // Why the file goes there, e.g. "exact title + artist" or "genre fallback"
+    pub reason: String,
+    // Written into the copy's track number tag (--write-track-numbers)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_number: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_total: Option<u32>,
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Collisions
+
+// What happens when a second file is planned onto a destination another file already claimed,
+// e.g. two "track.mp3" from different Contents/<Artist>/<Album> folders
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    // "track (2).mp3", "track (3).mp3", ...
+    #[default]
+    Counter,
+    // "track (Artist).mp3", with a counter if that is taken too
+    Artist,
+    // Files with the same contents share one copy, different files get a counter like Counter
+    SkipIdentical,
+    // Nothing is copied, the collisions are listed in the report
+    Fail,
+}
+
+// How a collision was settled
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resolution {
+    Renamed(PathBuf),
+    SharedIdentical,
+    Failed,
+}
+
+impl Resolution {
+    pub fn Describe(&self) -> String {
+        match self {
+            Resolution::Renamed(path) => format!("renamed to {}", path.display()),
+            Resolution::SharedIdentical => "identical file, copied once".to_string(),
+            Resolution::Failed => "run stopped".to_string(),
+        }
+    }
+}
+
+// A copy whose destination another file already claimed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Collision {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub claimed_by: PathBuf,
+    pub resolution: Resolution,
+}
+
+impl Collision {
+    pub fn Describe(&self) -> String {
+        format!("{} -> {} (already taken by {}, {})", self.source.display(), self.destination.display(),
+            self.claimed_by.display(), self.resolution.Describe())
+    }
+}
+
+// This compares two files by size, then by content hash
+// RETURNS: Whether both could be read and are the same
+pub fn SameContents(a: &Path, b: &Path) -> bool {
+    let size = |path: &Path| fs::metadata(path).map(|m| m.len()).ok();
+    if size(a).is_none() || size(a) != size(b) {
+        return false;
+    }
+    matches!((ContentHash(a), ContentHash(b)), (Ok(x), Ok(y)) if x == y)
+}
+
+// This adds a suffix before the extension, e.g. "track.mp3" -> "track (2).mp3"
+// RETURNS: Destination path with the suffix
+pub fn SuffixedDestination(destination: &Path, suffix: &str) -> PathBuf {
+    let stem = destination.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let name = match destination.extension() {
+        Some(extension) => format!("{} ({}).{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{} ({})", stem, suffix),
+    };
+    destination.with_file_name(name)
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Dry run preview
+
+// What a run would do, without doing it
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PlanPreview {
+    pub copies: Vec<CopyJob>,
+    pub unmatched: Vec<PathBuf>,
+    pub collisions: Vec<Collision>,
+    pub total_bytes: u64,
+}
+
+impl PlanPreview {
+    pub fn New(copies: Vec<CopyJob>, unmatched: Vec<PathBuf>, collisions: Vec<Collision>) -> Self {
+        let total_bytes = copies.iter().map(|job| fs::metadata(&job.source).map(|m| m.len()).unwrap_or(0)).sum();
+        Self { copies, unmatched, collisions, total_bytes }
+    }
+
+    pub fn WriteJson(&self, path: &Path) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, text + "\n")
+    }
+
+    // This writes the preview as plain text columns, for reading in a terminal or editor
+    // RETURNS: Nothing, writes the file
+    pub fn WriteTable(&self, path: &Path) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        let width = self.copies.iter().map(|job| job.source.display().to_string().len()).max().unwrap_or(0).max("SOURCE".len());
+
+        writeln!(file, "PLANNED COPIES ({}, {} bytes)", self.copies.len(), self.total_bytes)?;
+        writeln!(file, "{:<width$}  ->  DESTINATION  [REASON]", "SOURCE", width = width)?;
+        for job in &self.copies {
+            writeln!(file, "{:<width$}  ->  {}  [{}]", job.source.display(), job.destination.display(), job.reason, width = width)?;
+        }
+
+        writeln!(file)?;
+        writeln!(file, "UNMATCHED ({})", self.unmatched.len())?;
+        for path in &self.unmatched {
+            writeln!(file, "  {}", path.display())?;
+        }
+
+        writeln!(file)?;
+        writeln!(file, "COLLISIONS ({})", self.collisions.len())?;
+        for collision in &self.collisions {
+            writeln!(file, "  {}", collision.Describe())?;
+        }
+
+        Ok(())
+    }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Plan file
+
+// Default name for `rekordscratch plan`, picked up by `rekordscratch apply`
+pub const PLAN_FILE_NAME: &str = "RekordScratchPlan.json";
+
+// A playlist row a copy stands for, found again by name when the plan is applied
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlaylistRow {
+    // Playlist name as shown in RekordCrates (folders joined with "/", no extension)
+    pub playlist: String,
+    pub title: String,
+    pub position: usize,
+}
+
+// One copy in a plan file, with the playlist rows it is listed under
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedCopy {
+    #[serde(flatten)]
+    pub job: CopyJob,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub playlists: Vec<PlaylistRow>,
+}
+
+// Everything a run decided, written by `plan` and carried out as-is by `apply`
+// Copies can be removed, redirected or added by hand in between
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlanFile {
+    pub source_root: PathBuf,
+    // Folder RekordCrates lives in, playlist files are written there on apply
+    pub output_root: PathBuf,
+    #[serde(default)]
+    pub playlist_output: PlaylistOutput,
+    #[serde(default)]
+    pub rekordbox_xml: bool,
+    pub copies: Vec<PlannedCopy>,
+    // For reading only, apply ignores these
+    #[serde(default)]
+    pub unmatched: Vec<PathBuf>,
+    #[serde(default)]
+    pub collisions: Vec<Collision>,
+}
+
+impl PlanFile {
+    // This pairs each planned copy with the playlist rows that were placed on its destination
+    // RETURNS: Plan file contents, settings are filled in by the caller
+    pub fn New(sourceRoot: &Path, outputRoot: &Path, jobs: &[CopyJob], placed: &[PlacedTrack], unmatched: Vec<PathBuf>,
+        collisions: Vec<Collision>) -> Self {
+        let copies = jobs.iter().map(|job| PlannedCopy {
+            job: job.clone(),
+            playlists: placed.iter().filter(|p| p.destination == job.destination).map(|p| PlaylistRow {
+                playlist: p.entry.PlaylistName(),
+                title: p.entry.Title().to_string(),
+                position: p.entry.position,
+            }).collect(),
+        }).collect();
+
+        Self {
+            source_root: sourceRoot.to_path_buf(),
+            output_root: outputRoot.to_path_buf(),
+            playlist_output: PlaylistOutput::default(),
+            rekordbox_xml: false,
+            copies,
+            unmatched,
+            collisions,
+        }
+    }
+
+    // RETURNS: Plan file, or an error if it cannot be read or parsed
+    pub fn Load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn Save(&self, path: &Path) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, text + "\n")
+    }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
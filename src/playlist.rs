@@ -0,0 +1,624 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use walkdir::WalkDir;
+use std::path::Path;
+use std::io;
+use crate::readers::ReaderFor;
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Playlist records
+
+// A column Rekordbox can write into a playlist export, identified by its header
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Column {
+    Number,
+    Artwork,
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Bpm,
+    Rating,
+    Time,
+    Key,
+    DateAdded,
+    Composer,
+    Label,
+    Remixer,
+    OriginalArtist,
+    MixName,
+    Comments,
+    Year,
+    BitRate,
+    SampleRate,
+    PlayCount,
+    Location,
+}
+
+impl Column {
+    // This maps an exported header cell onto a known column, in any language Rekordbox ships with
+    // RETURNS: The column, or None for headers we do not know about
+    pub fn FromHeader(header: &str) -> Option<Column> {
+        let header = header.trim().to_lowercase();
+        HEADER_NAMES.iter()
+            .find(|(_, names)| names.iter().any(|name| name.to_lowercase() == header))
+            .map(|(column, _)| *column)
+    }
+
+    // This maps a COLLECTION TRACK attribute of a rekordbox.xml onto a known column
+    // RETURNS: The column, or None for attributes without a playlist export equivalent
+    pub fn FromXmlAttribute(attribute: &str) -> Option<Column> {
+        let column = match attribute {
+            "Name" => Column::Title,
+            "Artist" => Column::Artist,
+            "Album" => Column::Album,
+            "Genre" => Column::Genre,
+            "AverageBpm" => Column::Bpm,
+            "Rating" => Column::Rating,
+            "TotalTime" => Column::Time,
+            "Tonality" => Column::Key,
+            "DateAdded" => Column::DateAdded,
+            "Composer" => Column::Composer,
+            "Label" => Column::Label,
+            "Remixer" => Column::Remixer,
+            "Mix" => Column::MixName,
+            "Comments" => Column::Comments,
+            "Year" => Column::Year,
+            "BitRate" => Column::BitRate,
+            "SampleRate" => Column::SampleRate,
+            "PlayCount" => Column::PlayCount,
+            "Location" => Column::Location,
+            _ => return None,
+        };
+        Some(column)
+    }
+}
+
+// Header text per column, as written by a Rekordbox .txt export in each UI language
+// (English, German, French, Spanish, Italian, Portuguese, Japanese)
+// A word that means different columns across languages ("Tempo", "Local") is left out rather than guessed
+const HEADER_NAMES: &[(Column, &[&str])] = &[
+    (Column::Number, &["#"]),
+    (Column::Artwork, &["Artwork", "Illustration", "Ilustración", "Copertina", "Capa", "アートワーク"]),
+    (Column::Title, &["Track Title", "Titel", "Titre", "Título", "Titolo", "トラックタイトル"]),
+    (Column::Artist, &["Artist", "Interpret", "Artiste", "Artista", "アーティスト"]),
+    (Column::Album, &["Album", "Álbum", "アルバム"]),
+    (Column::Genre, &["Genre", "Género", "Genere", "Gênero", "ジャンル"]),
+    (Column::Bpm, &["BPM"]),
+    (Column::Rating, &["Rating", "Bewertung", "Classement", "Valoración", "Valutazione", "Classificação", "レーティング"]),
+    (Column::Time, &["Time", "Zeit", "Durée", "Tiempo", "Durata", "Duração", "時間"]),
+    (Column::Key, &["Key", "Tonart", "Tonalité", "Tonalidad", "Tonalità", "Tonalidade", "キー"]),
+    (Column::DateAdded, &["Date Added", "Hinzugefügt am", "Date d'ajout", "Fecha de adición", "Data di aggiunta", "Data de adição", "追加日"]),
+    (Column::Composer, &["Composer", "Komponist", "Compositeur", "Compositor", "Compositore", "作曲者"]),
+    (Column::Label, &["Label", "Sello", "Etichetta", "Gravadora", "レーベル"]),
+    (Column::Remixer, &["Remixer", "Remixeur", "Remezclador", "Remixador", "リミキサー"]),
+    (Column::OriginalArtist, &["Original Artist", "Original-Interpret", "Artiste original", "Artista original", "Artista originale",
+        "オリジナルアーティスト"]),
+    (Column::MixName, &["Mix Name", "Mix-Name", "Nom du mix", "Nombre de mezcla", "Nome mix", "Nome do mix", "ミックス名"]),
+    (Column::Comments, &["Comments", "Kommentare", "Commentaires", "Comentarios", "Commenti", "Comentários", "コメント"]),
+    (Column::Year, &["Year", "Jahr", "Année", "Año", "Anno", "Ano", "年"]),
+    (Column::BitRate, &["Bitrate", "Débit binaire", "Velocidad de bits", "Taxa de bits", "ビットレート"]),
+    (Column::SampleRate, &["Sample Rate", "Abtastrate", "Fréquence d'échantillonnage", "Frecuencia de muestreo",
+        "Frequenza di campionamento", "Taxa de amostragem", "サンプリングレート"]),
+    (Column::PlayCount, &["Play Count", "Wiedergaben", "Nombre de lectures", "Número de reproducciones", "Numero di riproduzioni",
+        "Contagem de reprodução", "再生回数"]),
+    (Column::Location, &["Location", "Speicherort", "Emplacement", "Ubicación", "Posizione", "Localização", "場所"]),
+];
+
+// One row of a playlist export
+// Every column is kept as exported, the known ones are also indexed by Column
+#[derive(Clone, Debug)]
+pub struct PlaylistTrack {
+    pub playlist: String,
+    pub position: usize,
+    pub playlist_length: usize,
+    pub columns: Vec<(String, String)>,
+    pub fields: HashMap<Column, String>,
+}
+
+impl PlaylistTrack {
+    pub fn Get(&self, column: Column) -> Option<&str> {
+        self.fields.get(&column).map(|value| value.as_str())
+    }
+
+    // This turns one row of cells into a record, using the header to name each cell
+    // Short rows simply leave the trailing columns out
+    // RETURNS: The playlist record
+    pub fn FromCells(cells: &[String], row: usize, headers: &[String], columns: &[Option<Column>], playlist: &str) -> PlaylistTrack {
+        let mut track = PlaylistTrack {
+            playlist: playlist.to_string(),
+            position: row,
+            playlist_length: 0,
+            columns: Vec::new(),
+            fields: HashMap::new(),
+        };
+
+        for (i, cell) in cells.iter().enumerate() {
+            let value = cell.trim().to_string();
+            let header = headers.get(i).cloned().unwrap_or_else(|| format!("Column {}", i + 1));
+
+            if let Some(Some(column)) = columns.get(i) {
+                if !value.is_empty() {
+                    track.fields.insert(*column, value.clone());
+                }
+            }
+            track.columns.push((header, value));
+        }
+
+        // The "#" column is the running order of the set, the row number stands in when it is missing
+        if let Some(number) = track.Get(Column::Number).and_then(|n| n.parse::<usize>().ok()) {
+            track.position = number;
+        }
+
+        track
+    }
+
+    pub fn Title(&self) -> &str {
+        self.Get(Column::Title).unwrap_or_default()
+    }
+
+    // Playlist name without the export extension, used for output folders and playlist files
+    // Nested playlists keep their folders, e.g. "Techno/Peak Time/Set"
+    pub fn PlaylistName(&self) -> String {
+        match self.playlist.rsplit_once('.') {
+            Some((name, _)) if ReaderFor(Path::new(&self.playlist)).is_some() => name.to_string(),
+            _ => self.playlist.clone(),
+        }
+    }
+
+    // Output names are padded to at least three digits so files sort in running order
+    pub fn PaddedPosition(&self) -> String {
+        let width = self.playlist_length.to_string().len().max(3);
+        format!("{:0width$}", self.position, width = width)
+    }
+}
+
+// Track title -> every playlist row carrying that title
+pub type TrackMap = HashMap<String, Vec<PlaylistTrack>>;
+
+// This strips characters that are not allowed in file names on Windows, macOS or Linux
+// "/" and "\" are replaced too, so the result is always a single path component, and "." or ".." become "_"
+// RETURNS: Safe file or folder name
+pub fn SanitiseFileName(name: &str) -> String {
+    let safe = name.chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string();
+    if safe.is_empty() { "_".to_string() } else { safe }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: TrackMap construction
+
+// This parses every playlist file under a directory and adds its rows to trackMap
+// The reader is picked by extension (.txt, .m3u, .m3u8, .csv), other files are ignored
+// Sub folders are walked too, "Techno/Peak Time/Set.txt" keeps its folders in the playlist name
+// A file that cannot be read or decoded is skipped so the rest of the playlists still load
+// RETURNS: One warning per skipped file, but modifies the trackMap
+pub fn BuildMapFromPlaylists(trackMap: &mut TrackMap, txtPath: &str) -> io::Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    let root = Path::new(txtPath);
+    if !root.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "not a directory"));
+    }
+
+    // Iterate through all playlist files in the directory tree
+    for entryResult in WalkDir::new(root).sort_by_file_name() {
+        let entry = match entryResult {
+            Ok(entry) => entry,
+            Err(e) => {
+                warnings.push(format!("Skipped part of {}: {}", txtPath, e));
+                continue;
+            }
+        };
+        let path = entry.path();
+
+        if entry.file_type().is_file() {
+            if let Some(reader) = ReaderFor(path) {
+                let playlist = RelativePlaylistName(root, path);
+                match reader.ReadPlaylist(path, &playlist) {
+                    Ok(rows) => AddPlaylistToMap(rows, trackMap),
+                    Err(e) => warnings.push(format!("Skipped {}: {}", path.display(), e)),
+                }
+            }
+        }
+    }
+    
+    Ok(warnings)
+}
+
+// This names a playlist by its path below the playlists folder, always with "/" separators
+// RETURNS: e.g. "Techno/Peak Time/Set.txt"
+fn RelativePlaylistName(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// This files every row of one playlist under its title
+// RETURNS: Nothing, modifies the trackMap
+fn AddPlaylistToMap(rows: Vec<PlaylistTrack>, map: &mut TrackMap) {
+    let playlistLength = rows.len();
+
+    for mut track in rows {
+        track.playlist_length = playlistLength;
+        let title = track.Title().to_string();
+        if !title.is_empty() {
+            map.entry(title).or_default().push(track);
+        }
+    }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Text decoding
+// Rekordbox's "Export playlist to .txt" writes UTF-16 LE with a BOM, hand-edited files are usually UTF-8
+
+// This sniffs the byte order mark and decodes the playlist file accordingly
+// RETURNS: Decoded text, or an InvalidData error naming the encoding that failed
+pub fn DecodePlaylistText(bytes: &[u8]) -> io::Result<String> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => DecodeUtf8(rest),
+        [0xFF, 0xFE, rest @ ..] => DecodeUtf16(rest, u16::from_le_bytes, "UTF-16 LE"),
+        [0xFE, 0xFF, rest @ ..] => DecodeUtf16(rest, u16::from_be_bytes, "UTF-16 BE"),
+
+        // No BOM: UTF-16 text is full of zero bytes, UTF-8 text never has them
+        _ => match GuessUtf16Order(bytes) {
+            Some(true) => DecodeUtf16(bytes, u16::from_le_bytes, "UTF-16 LE"),
+            Some(false) => DecodeUtf16(bytes, u16::from_be_bytes, "UTF-16 BE"),
+            None => DecodeUtf8(bytes),
+        },
+    }
+}
+
+fn DecodeUtf8(bytes: &[u8]) -> io::Result<String> {
+    String::from_utf8(bytes.to_vec())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("not valid UTF-8 ({})", e)))
+}
+
+fn DecodeUtf16(bytes: &[u8], toUnit: fn([u8; 2]) -> u16, encoding: &str) -> io::Result<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("odd byte count for {}", encoding)));
+    }
+
+    let units = bytes.chunks_exact(2).map(|pair| toUnit([pair[0], pair[1]]));
+    char::decode_utf16(units).collect::<Result<String, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("not valid {} ({})", encoding, e)))
+}
+
+// This guesses the byte order of BOM-less UTF-16 from where the zero bytes sit
+// RETURNS: Some(true) for little endian, Some(false) for big endian, None if it does not look like UTF-16
+fn GuessUtf16Order(bytes: &[u8]) -> Option<bool> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let evenZeros = bytes.iter().step_by(2).filter(|b| **b == 0).count();
+    let oddZeros = bytes.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    let pairs = bytes.len() / 2;
+
+    if oddZeros * 2 > pairs && evenZeros == 0 {
+        return Some(true);
+    }
+    if evenZeros * 2 > pairs && oddZeros == 0 {
+        return Some(false);
+    }
+    None
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: rekordbox.xml import
+// An old "Export Collection in xml format" file holds every playlist, so it can stand in for the .txt folder
+
+// A playlist node while the xml is being read, keys are resolved once the whole COLLECTION is known
+struct XmlPlaylist {
+    name: String,
+    keyIsLocation: bool,
+    keys: Vec<String>,
+}
+
+// This checks whether the playlists path points at a rekordbox.xml rather than a folder of .txt files
+// RETURNS: Boolean corresponding to if it is an xml file
+pub fn IsRekordboxXml(path: &str) -> bool {
+    let path = Path::new(path);
+    path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+}
+
+// This builds the trackMap from the COLLECTION and PLAYLISTS nodes of a rekordbox.xml
+// Nested playlist folders become "Folder/Sub Folder/Playlist" names
+// RETURNS: One warning per playlist entry that points at a missing track, but modifies the trackMap
+pub fn BuildMapFromXml(trackMap: &mut TrackMap, xmlPath: &Path) -> io::Result<Vec<String>> {
+    let mut reader = Reader::from_file(xmlPath).map_err(XmlError)?;
+    let mut buf = Vec::new();
+
+    let mut collection: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    let mut locations: HashMap<String, String> = HashMap::new();
+    let mut playlists: Vec<XmlPlaylist> = Vec::new();
+
+    // Open NODEs: Some(name) for folders, None for a playlist (whose data lives in playlists.last())
+    let mut nodeStack: Vec<Option<String>> = Vec::new();
+    let mut inCollection = false;
+
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(XmlError)?;
+        let isStart = matches!(event, Event::Start(_));
+
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => match element.name().as_ref() {
+                b"COLLECTION" => inCollection = isStart,
+                b"TRACK" if inCollection => {
+                    let attributes = XmlAttributes(element)?;
+                    let id = attributes.iter().find(|(k, _)| k == "TrackID").map(|(_, v)| v.clone());
+                    let location = attributes.iter().find(|(k, _)| k == "Location").map(|(_, v)| v.clone());
+                    if let Some(id) = id {
+                        if let Some(location) = location {
+                            locations.insert(location, id.clone());
+                        }
+                        collection.insert(id, attributes);
+                    }
+                }
+                b"TRACK" => {
+                    if let (Some(None), Some(playlist)) = (nodeStack.last(), playlists.last_mut()) {
+                        if let Some((_, key)) = XmlAttributes(element)?.into_iter().find(|(k, _)| k == "Key") {
+                            playlist.keys.push(key);
+                        }
+                    }
+                }
+                b"NODE" => {
+                    let attributes = XmlAttributes(element)?;
+                    let get = |key: &str| attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+                    // Names become folders in RekordCrates, so "AC/DC", "Why?" or ".." must not leave or split them
+                    if get("Type") == Some("1") {
+                        let mut path: Vec<&str> = nodeStack.iter().flatten().map(|s| s.as_str()).filter(|s| !s.is_empty()).collect();
+                        let name = SanitiseFileName(get("Name").unwrap_or("Unnamed playlist"));
+                        path.push(&name);
+                        playlists.push(XmlPlaylist {
+                            name: path.join("/"),
+                            keyIsLocation: get("KeyType") == Some("1"),
+                            keys: Vec::new(),
+                        });
+                        if isStart { nodeStack.push(None); }
+                    }
+                    else if isStart {
+                        // The top level folder is always called ROOT and is not part of any playlist path
+                        let name = get("Name").unwrap_or_default().to_string();
+                        nodeStack.push(if nodeStack.is_empty() && name == "ROOT" { Some(String::new()) } else { Some(SanitiseFileName(&name)) });
+                    }
+                }
+                _ => {}
+            },
+            Event::End(ref element) => match element.name().as_ref() {
+                b"COLLECTION" => inCollection = false,
+                b"NODE" => { nodeStack.pop(); }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    // Resolve every playlist entry against the collection
+    let mut warnings = Vec::new();
+    for playlist in playlists {
+        let playlistLength = playlist.keys.len();
+        for (i, key) in playlist.keys.iter().enumerate() {
+            let id = if playlist.keyIsLocation { locations.get(key) } else { Some(key) };
+            let Some(attributes) = id.and_then(|id| collection.get(id)) else {
+                warnings.push(format!("Playlist {} refers to unknown track {}", playlist.name, key));
+                continue;
+            };
+
+            let track = XmlTrackRecord(attributes, &playlist.name, i + 1, playlistLength);
+            let title = track.Title().to_string();
+            if !title.is_empty() {
+                trackMap.entry(title).or_default().push(track);
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+// This turns a COLLECTION TRACK into the same record a .txt row produces
+// RETURNS: The playlist record
+fn XmlTrackRecord(attributes: &[(String, String)], playlist: &str, position: usize, playlistLength: usize) -> PlaylistTrack {
+    let mut track = PlaylistTrack {
+        playlist: playlist.to_string(),
+        position,
+        playlist_length: playlistLength,
+        columns: attributes.to_vec(),
+        fields: HashMap::new(),
+    };
+
+    for (name, value) in attributes {
+        if let Some(column) = Column::FromXmlAttribute(name) {
+            if value.is_empty() { continue; }
+
+            // TotalTime is in seconds, .txt exports write mm:ss
+            let value = match (column, value.parse::<u64>()) {
+                (Column::Time, Ok(seconds)) => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+                _ => value.clone(),
+            };
+            track.fields.insert(column, value);
+        }
+    }
+
+    track
+}
+
+fn XmlAttributes(element: &BytesStart) -> io::Result<Vec<(String, String)>> {
+    let mut attributes = Vec::new();
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(XmlError)?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+        let value = attribute.unescape_value().map_err(XmlError)?.to_string();
+        attributes.push((key, value));
+    }
+    Ok(attributes)
+}
+
+fn XmlError(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid rekordbox.xml ({})", e))
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::ScratchDir;
+
+    const LIBRARY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<DJ_PLAYLISTS Version="1.0.0">
+  <COLLECTION Entries="2">
+    <TRACK TrackID="1" Name="Song" Artist="DJ X" TotalTime="332" Location="file://localhost/Music/song.mp3"/>
+    <TRACK TrackID="2" Name="Other" Artist="DJ Y" Location="file://localhost/Music/other.mp3"/>
+  </COLLECTION>
+  <PLAYLISTS>
+    <NODE Type="0" Name="ROOT" Count="2">
+      <NODE Type="0" Name="Techno: Peak" Count="1">
+        <NODE Name="AC/DC?" Type="1" KeyType="0" Entries="2">
+          <TRACK Key="1"/>
+          <TRACK Key="2"/>
+        </NODE>
+      </NODE>
+      <NODE Name=".." Type="1" KeyType="1" Entries="2">
+        <TRACK Key="file://localhost/Music/other.mp3"/>
+        <TRACK Key="99"/>
+      </NODE>
+    </NODE>
+  </PLAYLISTS>
+</DJ_PLAYLISTS>
+"#;
+
+    #[test]
+    fn XmlPlaylistsKeepFoldersAndPositions() {
+        let xml = ScratchDir::New("xml");
+        let mut map = TrackMap::new();
+        BuildMapFromXml(&mut map, &xml.Write("rekordbox.xml", LIBRARY)).unwrap();
+
+        let song = &map["Song"][0];
+        assert_eq!(song.playlist, "Techno_ Peak/AC_DC_");
+        assert_eq!(song.position, 1);
+        assert_eq!(song.playlist_length, 2);
+        assert_eq!(song.Get(Column::Artist), Some("DJ X"));
+        assert_eq!(song.Get(Column::Time), Some("05:32"));
+    }
+
+    #[test]
+    fn XmlPlaylistNamesCannotLeaveTheOutputFolder() {
+        let xml = ScratchDir::New("xml");
+        let mut map = TrackMap::new();
+        BuildMapFromXml(&mut map, &xml.Write("rekordbox.xml", LIBRARY)).unwrap();
+
+        for entry in map.values().flatten() {
+            for part in entry.playlist.split('/') {
+                assert!(!part.is_empty() && part != "." && part != "..", "{}", entry.playlist);
+                assert!(!part.contains([':', '?', '\\']), "{}", entry.playlist);
+            }
+        }
+    }
+
+    #[test]
+    fn XmlLocationKeysResolveAndUnknownKeysWarn() {
+        let xml = ScratchDir::New("xml");
+        let mut map = TrackMap::new();
+        let warnings = BuildMapFromXml(&mut map, &xml.Write("rekordbox.xml", LIBRARY)).unwrap();
+
+        assert!(map["Other"].iter().any(|entry| entry.playlist == "_"));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("99"));
+    }
+
+    // Header rows of a default .txt export per UI language: #, Artwork, Title, Artist, Album, Genre, BPM, Rating, Time, Key, Date Added
+    const EXPORT_HEADERS: &[&str] = &[
+        "#\tArtwork\tTrack Title\tArtist\tAlbum\tGenre\tBPM\tRating\tTime\tKey\tDate Added",
+        "#\tArtwork\tTitel\tInterpret\tAlbum\tGenre\tBPM\tBewertung\tZeit\tTonart\tHinzugefügt am",
+        "#\tIllustration\tTitre\tArtiste\tAlbum\tGenre\tBPM\tClassement\tDurée\tTonalité\tDate d'ajout",
+        "#\tIlustración\tTítulo\tArtista\tÁlbum\tGénero\tBPM\tValoración\tTiempo\tTonalidad\tFecha de adición",
+        "#\tCopertina\tTitolo\tArtista\tAlbum\tGenere\tBPM\tValutazione\tDurata\tTonalità\tData di aggiunta",
+        "#\tCapa\tTítulo\tArtista\tÁlbum\tGênero\tBPM\tClassificação\tDuração\tTonalidade\tData de adição",
+        "#\tアートワーク\tトラックタイトル\tアーティスト\tアルバム\tジャンル\tBPM\tレーティング\t時間\tキー\t追加日",
+    ];
+
+    #[test]
+    fn ExportHeadersMapInEveryLanguage() {
+        let expected = [Column::Number, Column::Artwork, Column::Title, Column::Artist, Column::Album, Column::Genre, Column::Bpm,
+            Column::Rating, Column::Time, Column::Key, Column::DateAdded];
+        for row in EXPORT_HEADERS {
+            let columns: Vec<Option<Column>> = row.split('\t').map(Column::FromHeader).collect();
+            assert_eq!(columns, expected.map(Some), "{}", row);
+        }
+    }
+
+    #[test]
+    fn HeaderNamesBelongToOneColumn() {
+        let mut seen: HashMap<String, Column> = HashMap::new();
+        for (column, names) in HEADER_NAMES {
+            for name in *names {
+                if let Some(other) = seen.insert(name.to_lowercase(), *column) {
+                    panic!("{} is listed for {:?} and {:?}", name, other, column);
+                }
+            }
+        }
+        assert_eq!(Column::FromHeader("Tempo"), None);
+        assert_eq!(Column::FromHeader("Local"), None);
+        assert_eq!(Column::FromHeader("Arte"), None);
+    }
+
+    #[test]
+    fn SanitiseFileNameKeepsOneSafeComponent() {
+        assert_eq!(SanitiseFileName("AC/DC"), "AC_DC");
+        assert_eq!(SanitiseFileName("Why?"), "Why_");
+        assert_eq!(SanitiseFileName(".."), "_");
+        assert_eq!(SanitiseFileName("../x"), ".._x");
+        assert_eq!(SanitiseFileName("Vol. 2. "), "Vol. 2");
+    }
+
+
+    fn Utf16(text: &str, bom: &[u8], toBytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+        let mut bytes = bom.to_vec();
+        bytes.extend(text.encode_utf16().flat_map(toBytes));
+        bytes
+    }
+
+    #[test]
+    fn DecodeReadsEveryBomAndGuessesWithoutOne() {
+        let text = "Track Title\tArtist\nCafé\tDJ X";
+        assert_eq!(DecodePlaylistText(&Utf16(text, &[0xFF, 0xFE], u16::to_le_bytes)).unwrap(), text);
+        assert_eq!(DecodePlaylistText(&Utf16(text, &[0xFE, 0xFF], u16::to_be_bytes)).unwrap(), text);
+        assert_eq!(DecodePlaylistText(&Utf16(text, &[], u16::to_le_bytes)).unwrap(), text);
+        assert_eq!(DecodePlaylistText(&Utf16(text, &[], u16::to_be_bytes)).unwrap(), text);
+
+        let mut utf8 = vec![0xEF, 0xBB, 0xBF];
+        utf8.extend(text.as_bytes());
+        assert_eq!(DecodePlaylistText(&utf8).unwrap(), text);
+        assert_eq!(DecodePlaylistText(text.as_bytes()).unwrap(), text);
+        assert_eq!(DecodePlaylistText(b"").unwrap(), "");
+    }
+
+    #[test]
+    fn DecodeNamesTheEncodingThatFailed() {
+        let error = DecodePlaylistText(&[0xFF, 0xFE, 0x41]).unwrap_err();
+        assert!(error.to_string().contains("UTF-16 LE"));
+        let error = DecodePlaylistText(&[0xFF, 0xFE, 0x00, 0xD8, 0x41, 0x00]).unwrap_err();
+        assert!(error.to_string().contains("UTF-16 LE"));
+        let error = DecodePlaylistText(&[0x41, 0xC3, 0x28]).unwrap_err();
+        assert!(error.to_string().contains("UTF-8"));
+    }
+}
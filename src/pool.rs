@@ -0,0 +1,30 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Worker pool
+
+// This runs work over every item on at most `workers` threads, each thread pulls the next item when it is free
+// The threads are scoped, so work can borrow from the caller (app state, the trackMap, counters)
+// RETURNS: One result per item, in the same order as items
+pub fn RunPool<T: Sync, R: Send>(workers: usize, items: &[T], work: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..items.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else { break; };
+                let result = work(item);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().map(|result| result.expect("every item is processed")).collect()
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
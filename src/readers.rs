@@ -0,0 +1,312 @@
+use std::path::Path;
+use std::fs;
+use std::io;
+use crate::playlist::{Column, DecodePlaylistText, PlaylistTrack};
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Reader registry
+
+// A playlist file format RekordScratch can rebuild from
+// Every reader produces the same PlaylistTrack rows, so matching does not care where a playlist came from
+pub trait PlaylistReader: Sync {
+    // Lower case extensions (without the dot) this reader handles
+    fn Extensions(&self) -> &'static [&'static str];
+
+    // RETURNS: Every row of the playlist in running order, or an error if the file is unusable
+    fn ReadPlaylist(&self, path: &Path, playlist: &str) -> io::Result<Vec<PlaylistTrack>>;
+}
+
+static READERS: &[&dyn PlaylistReader] = &[&TxtReader, &M3uReader, &CsvReader];
+
+// This picks the reader for a playlist file by its extension
+// RETURNS: The reader, or None if the file is not a playlist we understand
+pub fn ReaderFor(path: &Path) -> Option<&'static dyn PlaylistReader> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    READERS.iter().copied().find(|reader| reader.Extensions().contains(&extension.as_str()))
+}
+
+fn InvalidData(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Rekordbox .txt
+
+// Rekordbox's "Export playlist to .txt": tab separated, first row is the header
+pub struct TxtReader;
+
+impl PlaylistReader for TxtReader {
+    fn Extensions(&self) -> &'static [&'static str] {
+        &["txt"]
+    }
+
+    // Columns are located by their header names, so exports with hidden or reordered columns still parse
+    fn ReadPlaylist(&self, path: &Path, playlist: &str) -> io::Result<Vec<PlaylistTrack>> {
+        let contents = DecodePlaylistText(&fs::read(path)?)?;
+
+        let mut lines = contents.lines();
+        let headers: Vec<String> = match lines.next() {
+            Some(header) => header.split('\t').map(|h| h.trim().to_string()).collect(),
+            None => return Ok(Vec::new()),
+        };
+        let columns: Vec<Option<Column>> = headers.iter().map(|h| Column::FromHeader(h)).collect();
+
+        if !columns.contains(&Some(Column::Title)) {
+            return Err(InvalidData("no Track Title column in header"));
+        }
+
+        // Iterate through each entry in file
+        let rows = lines.filter(|line| !line.trim().is_empty()).enumerate()
+            .map(|(i, line)| {
+                let cells: Vec<String> = line.split('\t').map(str::to_string).collect();
+                PlaylistTrack::FromCells(&cells, i + 1, &headers, &columns, playlist)
+            })
+            .collect();
+
+        Ok(rows)
+    }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: M3U / M3U8
+
+// Plain or extended M3U, as written by most players and DJ software
+// "#EXTINF:<seconds>,<artist> - <title>" describes the path on the following line
+pub struct M3uReader;
+
+impl PlaylistReader for M3uReader {
+    fn Extensions(&self) -> &'static [&'static str] {
+        &["m3u", "m3u8"]
+    }
+
+    fn ReadPlaylist(&self, path: &Path, playlist: &str) -> io::Result<Vec<PlaylistTrack>> {
+        let contents = DecodePlaylistText(&fs::read(path)?)?;
+        let headers: Vec<String> = ["Location", "Artist", "Track Title", "Time"].map(String::from).to_vec();
+        let columns: Vec<Option<Column>> = vec![Some(Column::Location), Some(Column::Artist), Some(Column::Title), Some(Column::Time)];
+
+        let mut rows = Vec::new();
+        let mut extinf: Option<(String, String, String)> = None;
+
+        for line in contents.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if let Some(info) = line.strip_prefix("#EXTINF:") {
+                extinf = Some(ParseExtinf(info));
+                continue;
+            }
+            if line.starts_with('#') { continue; }
+
+            // Without #EXTINF the file name is the best title we have
+            let (artist, title, time) = extinf.take().unwrap_or_else(|| {
+                let stem = Path::new(&line.replace('\\', "/")).file_stem()
+                    .map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                (String::new(), stem, String::new())
+            });
+
+            let cells = vec![line.to_string(), artist, title, time];
+            rows.push(PlaylistTrack::FromCells(&cells, rows.len() + 1, &headers, &columns, playlist));
+        }
+
+        Ok(rows)
+    }
+}
+
+// This splits the text after "#EXTINF:" into artist, title and mm:ss duration
+// RETURNS: (artist, title, time), artist is empty when the display name has no " - "
+fn ParseExtinf(info: &str) -> (String, String, String) {
+    let (seconds, display) = info.split_once(',').unwrap_or(("-1", info));
+
+    // Extended attributes (tvg-id="..." etc.) may follow the duration
+    let seconds = seconds.split_whitespace().next().and_then(|s| s.parse::<i64>().ok()).unwrap_or(-1);
+    let time = if seconds >= 0 { format!("{:02}:{:02}", seconds / 60, seconds % 60) } else { String::new() };
+
+    match display.split_once(" - ") {
+        Some((artist, title)) => (artist.trim().to_string(), title.trim().to_string(), time),
+        None => (String::new(), display.trim().to_string(), time),
+    }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: CSV
+
+// CSV exports from streaming tools and spreadsheets, delimiter is sniffed from the header
+pub struct CsvReader;
+
+impl PlaylistReader for CsvReader {
+    fn Extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn ReadPlaylist(&self, path: &Path, playlist: &str) -> io::Result<Vec<PlaylistTrack>> {
+        let contents = DecodePlaylistText(&fs::read(path)?)?;
+        let firstLine = contents.lines().next().unwrap_or_default();
+        let delimiter = [',', ';', '\t'].into_iter()
+            .max_by_key(|d| firstLine.matches(*d).count())
+            .unwrap_or(',');
+
+        let mut records = ParseCsv(&contents, delimiter).into_iter();
+        let headers: Vec<String> = match records.next() {
+            Some(header) => header.iter().map(|h| h.trim().to_string()).collect(),
+            None => return Ok(Vec::new()),
+        };
+        let columns: Vec<Option<Column>> = headers.iter().map(|h| CsvColumn(h)).collect();
+
+        if !columns.contains(&Some(Column::Title)) {
+            return Err(InvalidData("no title column in CSV header"));
+        }
+
+        let rows = records.filter(|cells| cells.iter().any(|c| !c.trim().is_empty())).enumerate()
+            .map(|(i, mut cells)| {
+                // Durations in milliseconds are turned into the mm:ss a Rekordbox Time column holds
+                for (cell, header) in cells.iter_mut().zip(&headers) {
+                    if IsMillisecondsHeader(header) {
+                        if let Ok(ms) = cell.trim().parse::<u64>() {
+                            *cell = format!("{:02}:{:02}", ms / 60000, (ms / 1000) % 60);
+                        }
+                    }
+                }
+                PlaylistTrack::FromCells(&cells, i + 1, &headers, &columns, playlist)
+            })
+            .collect();
+
+        Ok(rows)
+    }
+}
+
+// This maps CSV headers, which use streaming service wording on top of the Rekordbox ones
+// RETURNS: The column, or None for headers we do not know about
+fn CsvColumn(header: &str) -> Option<Column> {
+    let column = match header.trim().to_lowercase().as_str() {
+        "track name" | "title" | "song" | "song name" | "name" | "track" => Column::Title,
+        "artist name" | "artist name(s)" | "artists" | "artist(s)" => Column::Artist,
+        "album name" | "release" => Column::Album,
+        "genres" => Column::Genre,
+        "tempo" => Column::Bpm,
+        "duration" | "length" | "duration (ms)" | "duration_ms" | "track duration (ms)" => Column::Time,
+        "position" | "track number" => Column::Number,
+        "record label" => Column::Label,
+        "release date" => Column::Year,
+        "path" | "file" | "filename" => Column::Location,
+        _ => return Column::FromHeader(header),
+    };
+    Some(column)
+}
+
+fn IsMillisecondsHeader(header: &str) -> bool {
+    let header = header.to_lowercase();
+    header.contains("(ms)") || header.ends_with("_ms")
+}
+
+// This splits CSV text into records, honouring quoted cells (including delimiters, newlines and "" escapes)
+// RETURNS: One Vec of cells per record
+fn ParseCsv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut cell = String::new();
+    let mut inQuotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if inQuotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => { cell.push('"'); chars.next(); }
+                '"' => inQuotes = false,
+                _ => cell.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => inQuotes = true,
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut cell));
+                records.push(std::mem::take(&mut record));
+            }
+            _ if c == delimiter => record.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+
+    if !cell.is_empty() || !record.is_empty() {
+        record.push(cell);
+        records.push(record);
+    }
+    records
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::ScratchDir;
+
+    #[test]
+    fn ExtinfSplitsArtistTitleAndTime() {
+        assert_eq!(ParseExtinf("332,DJ X - Song"), ("DJ X".to_string(), "Song".to_string(), "05:32".to_string()));
+        assert_eq!(ParseExtinf("-1,Song"), (String::new(), "Song".to_string(), String::new()));
+        assert_eq!(ParseExtinf("61 tvg-id=\"x\",A - B - C"), ("A".to_string(), "B - C".to_string(), "01:01".to_string()));
+        assert_eq!(ParseExtinf("Song without duration"), (String::new(), "Song without duration".to_string(), String::new()));
+    }
+
+    #[test]
+    fn CsvHonoursQuotesEscapesAndNewlines() {
+        let records = ParseCsv("Name,Artist\r\n\"Song, Part 1\",\"DJ \"\"X\"\"\"\n\"Two\nLines\",Y", ',');
+        assert_eq!(records, vec![
+            vec!["Name".to_string(), "Artist".to_string()],
+            vec!["Song, Part 1".to_string(), "DJ \"X\"".to_string()],
+            vec!["Two\nLines".to_string(), "Y".to_string()],
+        ]);
+        assert_eq!(ParseCsv("a;b;c", ';'), vec![vec!["a".to_string(), "b".to_string(), "c".to_string()]]);
+    }
+
+    #[test]
+    fn CsvReaderMapsStreamingHeadersAndMilliseconds() {
+        let dir = ScratchDir::New("csv");
+        let path = dir.Write("set.csv", b"Track Name;Artist Name(s);Duration (ms)\nSong;DJ X;332000\n;;\n");
+        let rows = CsvReader.ReadPlaylist(&path, "set.csv").unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].Title(), "Song");
+        assert_eq!(rows[0].Get(Column::Artist), Some("DJ X"));
+        assert_eq!(rows[0].Get(Column::Time), Some("05:32"));
+    }
+
+    #[test]
+    fn M3uReaderFallsBackToFileName() {
+        let dir = ScratchDir::New("m3u");
+        let path = dir.Write("set.m3u8", b"#EXTM3U\n#EXTINF:332,DJ X - Song\nMusic/song.mp3\nC:\\Music\\Other Track.mp3\n");
+        let rows = M3uReader.ReadPlaylist(&path, "set.m3u8").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].Title(), rows[0].Get(Column::Artist), rows[0].position), ("Song", Some("DJ X"), 1));
+        assert_eq!((rows[1].Title(), rows[1].position), ("Other Track", 2));
+    }
+
+    #[test]
+    fn TxtReaderNeedsATitleColumn() {
+        let dir = ScratchDir::New("txt");
+        let path = dir.Write("set.txt", b"#\tTrack Title\tArtist\n1\tSong\tDJ X\n\n");
+        let rows = TxtReader.ReadPlaylist(&path, "set.txt").unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].Get(Column::Artist), Some("DJ X"));
+
+        let path = dir.Write("broken.txt", b"Artist\tGenre\nDJ X\tTechno\n");
+        assert!(TxtReader.ReadPlaylist(&path, "broken.txt").is_err());
+    }
+
+    #[test]
+    fn ReaderIsPickedByExtension() {
+        assert!(ReaderFor(Path::new("Set.TXT")).is_some());
+        assert!(ReaderFor(Path::new("Set.m3u8")).is_some());
+        assert!(ReaderFor(Path::new("Set.csv")).is_some());
+        assert!(ReaderFor(Path::new("cover.jpg")).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+use crate::export::PlacedTrack;
+use crate::playlist::PlaylistTrack;
+use crate::track::Track;
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Review items
+
+// How many playlist rows are offered for each track
+pub const CANDIDATE_LIMIT: usize = 8;
+
+// Why a track was put in front of the user
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReviewReason {
+    NotMatched,
+    Ambiguous,
+    LowConfidence(f64),
+}
+
+impl ReviewReason {
+    pub fn Describe(&self) -> String {
+        match self {
+            ReviewReason::NotMatched => "Not matched".to_string(),
+            ReviewReason::Ambiguous => "Ambiguous match".to_string(),
+            ReviewReason::LowConfidence(confidence) => format!("Low confidence match ({:.0}%)", confidence * 100.0),
+        }
+    }
+}
+
+// A file on the USB that needs a decision, with the rows it could belong to
+#[derive(Clone, Debug)]
+pub struct ReviewItem {
+    pub track: Track,
+    pub reason: ReviewReason,
+    // Title (or file stem) the candidates were ranked against
+    pub query: String,
+    pub candidates: Vec<(PlaylistTrack, f64)>,
+    // Where the run already copied the file, replaced when a candidate is assigned
+    pub copies: Vec<PathBuf>,
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Review session
+
+// The after-run screen that walks through every unmatched, ambiguous and low confidence track
+pub struct ReviewSession {
+    pub items: Vec<ReviewItem>,
+    pub current: usize,
+    pub selected: usize,
+    // Some while the user is typing a search
+    pub search: Option<String>,
+    // Every placement of the run, kept up to date so playlist files can be rewritten afterwards
+    pub placed: Vec<PlacedTrack>,
+    pub assigned: usize,
+    pub skipped: usize,
+    // USB root the run read from, overrides are stored relative to it
+    pub source_root: PathBuf,
+}
+
+impl ReviewSession {
+    pub fn New(items: Vec<ReviewItem>, placed: Vec<PlacedTrack>, sourceRoot: PathBuf) -> Self {
+        Self { items, current: 0, selected: 0, search: None, placed, assigned: 0, skipped: 0, source_root: sourceRoot }
+    }
+
+    pub fn Current(&self) -> Option<&ReviewItem> {
+        self.items.get(self.current)
+    }
+
+    pub fn SelectedCandidate(&self) -> Option<&PlaylistTrack> {
+        self.Current()?.candidates.get(self.selected).map(|(entry, _)| entry)
+    }
+
+    pub fn IsFinished(&self) -> bool {
+        self.current >= self.items.len()
+    }
+
+    pub fn SelectNext(&mut self) {
+        let count = self.Current().map(|item| item.candidates.len()).unwrap_or(0);
+        if count > 0 {
+            self.selected = (self.selected + 1) % count;
+        }
+    }
+
+    pub fn SelectPrevious(&mut self) {
+        let count = self.Current().map(|item| item.candidates.len()).unwrap_or(0);
+        if count > 0 {
+            self.selected = (self.selected + count - 1) % count;
+        }
+    }
+
+    pub fn Skip(&mut self) {
+        self.skipped += 1;
+        self.Advance();
+    }
+
+    // This swaps the current track's copies for the new placements from a hand assignment
+    // A copy shared with another source (skip-identical) stays as long as that source is still placed there
+    // RETURNS: Copies that are no longer wanted and should be deleted
+    pub fn Assign(&mut self, newPlaced: Vec<PlacedTrack>) -> Vec<PathBuf> {
+        let Some(item) = self.items.get_mut(self.current) else { return Vec::new(); };
+        self.placed.retain(|p| p.track.path != item.track.path);
+
+        let kept: Vec<&Path> = newPlaced.iter().map(|p| p.destination.as_path()).collect();
+        let stale: Vec<PathBuf> = item.copies.iter()
+            .filter(|copy| !kept.contains(&copy.as_path()) && !self.placed.iter().any(|p| &p.destination == *copy))
+            .cloned().collect();
+
+        item.copies = newPlaced.iter().map(|p| p.destination.clone()).collect();
+        self.placed.extend(newPlaced);
+
+        self.assigned += 1;
+        self.Advance();
+        stale
+    }
+
+    pub fn SetCandidates(&mut self, candidates: Vec<(PlaylistTrack, f64)>) {
+        if let Some(item) = self.items.get_mut(self.current) {
+            item.candidates = candidates;
+        }
+        self.selected = 0;
+    }
+
+    fn Advance(&mut self) {
+        self.current += 1;
+        self.selected = 0;
+        self.search = None;
+    }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn Placed(source: &str, destination: &str) -> PlacedTrack {
+        let headers = vec!["Track Title".to_string()];
+        let entry = PlaylistTrack::FromCells(&["Track".to_string()], 1, &headers, &[Some(crate::playlist::Column::Title)], "Set.txt");
+        PlacedTrack { entry, destination: PathBuf::from(destination), track: Track { path: PathBuf::from(source), ..Track::default() } }
+    }
+
+    fn Item(source: &str, copies: &[&str]) -> ReviewItem {
+        ReviewItem {
+            track: Track { path: PathBuf::from(source), ..Track::default() },
+            reason: ReviewReason::Ambiguous,
+            query: "Track".to_string(),
+            candidates: Vec::new(),
+            copies: copies.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    #[test]
+    fn SharedCopyIsKeptWhileAnotherSourceUsesIt() {
+        // B was identical to A under skip-identical, so both are placed at A's copy
+        let placed = vec![Placed("/usb/A/track.mp3", "/out/Set/track.mp3"), Placed("/usb/B/track.mp3", "/out/Set/track.mp3")];
+        let items = vec![Item("/usb/A/track.mp3", &["/out/Set/track.mp3"]), Item("/usb/B/track.mp3", &["/out/Set/track.mp3"])];
+        let mut session = ReviewSession::New(items, placed, PathBuf::from("/usb"));
+
+        let stale = session.Assign(vec![Placed("/usb/A/track.mp3", "/out/Other/track.mp3")]);
+        assert!(stale.is_empty());
+        assert!(session.placed.iter().any(|p| p.track.path == Path::new("/usb/B/track.mp3") && p.destination == Path::new("/out/Set/track.mp3")));
+
+        let stale = session.Assign(vec![Placed("/usb/B/track.mp3", "/out/Else/track.mp3")]);
+        assert_eq!(stale, vec![PathBuf::from("/out/Set/track.mp3")]);
+        assert_eq!(session.placed.len(), 2);
+    }
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Test scratch folders
+
+// Keeps the folders of tests running in parallel apart
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+// A folder under the system temp dir for test fixtures, removed with everything in it when dropped
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    pub fn New(name: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("RekordScratch_{}_{}_{}", name, std::process::id(), id));
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub fn Path(&self) -> &Path {
+        &self.path
+    }
+
+    // This writes a fixture file, creating the folders above it
+    // RETURNS: Full path of the file
+    pub fn Write(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------
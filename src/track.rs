@@ -0,0 +1,70 @@
+use lofty::{read_from_path, Accessor, AudioFile, Tag, TaggedFileExt};
+use std::path::{Path, PathBuf};
+use crate::matching::TrackQuery;
+
+// -------------------------------------------------------------------------------------------------------------------------------------
+// REGION: Track record
+
+// Everything RekordScratch knows about a file on the USB, read once per run
+// Matching, the genre fallback and playlist export all work from this instead of re-reading the file
+#[derive(Clone, Debug, Default)]
+pub struct Track {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub seconds: Option<u64>,
+    pub bitrate: Option<u32>,
+    pub sample_rate: Option<u32>,
+}
+
+impl Track {
+    // This reads audio properties and every tag in the file (ID3v1, ID3v2, APE, Vorbis comments, MP4, RIFF INFO, AIFF text)
+    // The primary tag wins, the others fill in whatever it is missing, e.g. an empty ID3v2 artist backed by ID3v1
+    // RETURNS: Track, or error if lofty cannot read the file at all
+    pub fn Read(path: &Path) -> anyhow::Result<Self> {
+        let taggedFile = read_from_path(path)?;
+        let properties = taggedFile.properties();
+        let seconds = properties.duration().as_secs();
+
+        let primary = taggedFile.primary_tag_type();
+        let mut tags: Vec<&Tag> = taggedFile.tags().iter().collect();
+        tags.sort_by_key(|tag| tag.tag_type() != primary);
+
+        let first = |field: fn(&Tag) -> Option<String>| tags.iter().find_map(|tag| field(tag).filter(|v| !v.trim().is_empty()));
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            title: first(|tag| tag.title().map(|v| v.to_string())),
+            artist: first(|tag| tag.artist().map(|v| v.to_string())),
+            album: first(|tag| tag.album().map(|v| v.to_string())),
+            genre: first(|tag| tag.genre().map(|v| v.to_string())),
+            seconds: if seconds > 0 { Some(seconds) } else { None },
+            bitrate: properties.audio_bitrate(),
+            sample_rate: properties.sample_rate(),
+        })
+    }
+
+    // A file lofty could not read, only its path is known
+    pub fn Untagged(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), ..Self::default() }
+    }
+
+    // RETURNS: Match query, None if the file has no title
+    pub fn Query(&self) -> Option<TrackQuery> {
+        Some(TrackQuery {
+            title: self.title.clone()?,
+            artist: self.artist.clone(),
+            seconds: self.seconds,
+        })
+    }
+
+    // RETURNS: File name without extension, empty if there is none
+    pub fn Stem(&self) -> &str {
+        self.path.file_stem().and_then(|s| s.to_str()).unwrap_or_default()
+    }
+}
+
+// ENDREGION
+// -------------------------------------------------------------------------------------------------------------------------------------